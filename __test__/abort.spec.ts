@@ -0,0 +1,29 @@
+import test from 'ava'
+
+import { benchmarkTestAsync, executeTestAsync, initRuntime } from '../index'
+
+const artifactPath = 'contracts/Avg_Unit_Test.json'
+const testName = 'test_Avg_OneOperandEvenTheOtherOdd()'
+
+// A single blocking thread, so that a second job has to wait in the queue. Each spec file runs in
+// its own process, which keeps this runtime from affecting the other tests.
+initRuntime({ maxBlockingThreads: 1 })
+
+test('aborting a queued job rejects before it gets a thread', async (t) => {
+  let blockerDone = false
+  let onFirstIteration!: () => void
+  const firstIteration = new Promise<void>((resolve) => (onFirstIteration = resolve))
+  const blocker = benchmarkTestAsync(artifactPath, testName, 100, () => onFirstIteration()).then(() => {
+    blockerDone = true
+  })
+  await firstIteration
+
+  const controller = new AbortController()
+  const queued = executeTestAsync(artifactPath, testName, null, null, controller.signal)
+  controller.abort()
+  const err = await t.throwsAsync(queued)
+  t.like(err, { code: 'ABORTED', phase: 'load' })
+  t.false(blockerDone)
+
+  await blocker
+})
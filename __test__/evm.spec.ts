@@ -0,0 +1,34 @@
+import { readFileSync } from 'node:fs'
+
+import test from 'ava'
+
+import { Evm } from '../index'
+
+const artifactPath = 'contracts/Avg_Unit_Test.json'
+
+test('deploys an in-memory artifact like one from a file', (t) => {
+  const fromFile = new Evm().deployArtifact(artifactPath)
+  const artifact = JSON.parse(readFileSync(artifactPath, 'utf8'))
+  const fromObject = new Evm().deployArtifactFromMemory(artifact)
+  const fromString = new Evm().deployArtifactFromMemory(JSON.stringify(artifact))
+  t.is(fromObject.createdAddress, fromFile.createdAddress)
+  t.is(fromString.createdAddress, fromFile.createdAddress)
+})
+
+test('deploys raw bytecode as is', (t) => {
+  const evm = new Evm()
+  const code = Buffer.from('602a60005260206000f3', 'hex')
+  const { createdAddress } = evm.deployArtifactFromMemory(code)
+  t.is(evm.getAccount(createdAddress!)?.code, '0x602a60005260206000f3')
+})
+
+test('failed deployments throw the error code and phase', (t) => {
+  const evm = new Evm()
+  const missing = t.throws(() => evm.deployArtifact('contracts/Missing.json'))
+  t.like(missing, { code: 'ARTIFACT_NOT_FOUND', phase: 'load', path: 'contracts/Missing.json' })
+  const invalid = t.throws(() => evm.deployArtifactFromMemory('{}'))
+  t.like(invalid, { code: 'INVALID_ARTIFACT', phase: 'load' })
+  const reverting = { bytecode: { object: '0x60006000fd' }, deployedBytecode: { object: '0x' } }
+  const reverted = t.throws(() => evm.deployArtifactFromMemory(reverting))
+  t.like(reverted, { code: 'TEST_REVERTED', phase: 'result-handling', output: '0x' })
+})
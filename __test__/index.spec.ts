@@ -1,9 +1,113 @@
 import test from 'ava'
 
-import { executeTest } from '../index'
+import {
+  benchmarkTestAsync,
+  executeArtifactSync,
+  executeTestAsync,
+  executeTestsAsync,
+  executeTestSync,
+  initRuntime,
+  IntegerFormat,
+  latencyHistogram,
+  TestRunner,
+} from '../index'
+import { executeTestInWorker } from '../worker'
+
+const artifactPath = 'contracts/Avg_Unit_Test.json'
+const testName = 'test_Avg_OneOperandEvenTheOtherOdd()'
 
 test('sync function from native code', (t) => {
-  const artifactPath = "contracts/Avg_Unit_Test.json";
-  const testName = "test_Avg_OneOperandEvenTheOtherOdd()";
-  t.true(executeTest(artifactPath, testName) > 0)
+  t.true(executeTestSync(artifactPath, testName).durationNs > 0)
+})
+
+test('async function resolves with the result', async (t) => {
+  const result = await executeTestAsync(artifactPath, testName)
+  t.true(result.durationNs > 0)
+})
+
+test('aborted signal rejects with ABORTED', async (t) => {
+  const err = await t.throwsAsync(executeTestAsync(artifactPath, testName, null, null, AbortSignal.abort()))
+  t.is((err as any).code, 'ABORTED')
+})
+
+test('signal that is never aborted does not affect the result', async (t) => {
+  const result = await executeTestAsync(artifactPath, testName, null, null, new AbortController().signal)
+  t.true(result.durationNs > 0)
+})
+
+test('missing artifact throws ARTIFACT_NOT_FOUND', (t) => {
+  const err = t.throws(() => executeTestSync('contracts/Missing.json', testName))
+  t.like(err, { code: 'ARTIFACT_NOT_FOUND', phase: 'load', path: 'contracts/Missing.json' })
+})
+
+test('unknown test throws UNKNOWN_TEST', (t) => {
+  const err = t.throws(() => executeTestSync(artifactPath, 'test_Missing()'))
+  t.like(err, { code: 'UNKNOWN_TEST', testName: 'test_Missing()' })
+})
+
+test('invalid in-memory artifact throws INVALID_ARTIFACT', (t) => {
+  const err = t.throws(() => executeArtifactSync('{}', testName))
+  t.like(err, { code: 'INVALID_ARTIFACT' })
+})
+
+test('reverted test throws TEST_REVERTED with its gas', (t) => {
+  const err = t.throws(() => executeTestSync(artifactPath, 'test_Avg_BothOperandsZero()'))
+  t.like(err, { code: 'TEST_REVERTED', phase: 'result-handling', output: '0x' })
+  t.is(typeof (err as any).gasUsed, 'bigint')
+})
+
+test('async errors carry the same code', async (t) => {
+  const err = await t.throwsAsync(executeTestAsync(artifactPath, 'test_Missing()'))
+  t.like(err, { code: 'UNKNOWN_TEST' })
+})
+
+test('worker thread resolves with the result', async (t) => {
+  const result = await executeTestInWorker(artifactPath, testName, null, { integerFormat: IntegerFormat.Hex })
+  t.true(result.durationNs > 0)
+})
+
+test('worker thread rejects with the error code', async (t) => {
+  const err = await t.throwsAsync(executeTestInWorker(artifactPath, 'test_Missing()'))
+  t.like(err, { code: 'UNKNOWN_TEST', testName: 'test_Missing()' })
+})
+
+test('test runner reports the outcome of every test in order', async (t) => {
+  const runner = new TestRunner({ size: 2 })
+  t.is(runner.size, 2)
+  const outcomes = await runner.runMany([
+    { testArtifactPath: artifactPath, testName },
+    { testArtifactPath: artifactPath, testName: 'test_Missing()' },
+    { testArtifactPath: artifactPath, testName: 'test_Avg_BothOperandsZero()' },
+  ])
+  t.deepEqual(
+    outcomes.map(({ testName, result, error }) => [testName, result !== undefined, error?.code]),
+    [
+      [testName, true, undefined],
+      ['test_Missing()', false, 'UNKNOWN_TEST'],
+      ['test_Avg_BothOperandsZero()', false, 'TEST_REVERTED'],
+    ],
+  )
+})
+
+test('initRuntime rejects zero thread counts and stack sizes', (t) => {
+  t.throws(() => initRuntime({ workerThreads: 0 }), { message: 'workerThreads must be greater than 0' })
+  t.throws(() => initRuntime({ maxBlockingThreads: 0 }), { message: 'maxBlockingThreads must be greater than 0' })
+  t.throws(() => initRuntime({ threadStackSize: 0 }), { message: 'threadStackSize must be greater than 0' })
+})
+
+test('latency histogram passes its integers as bigint or hex', (t) => {
+  t.like(latencyHistogram([1000, 2000, 3000]), { count: 3n, minNs: 1000n })
+  t.like(latencyHistogram([1000, 2000, 3000], null, IntegerFormat.Hex), { count: '0x3', minNs: '0x3e8' })
+})
+
+test('every progress callback runs before the promise resolves', async (t) => {
+  const tests = Array.from({ length: 5 }, () => ({ testArtifactPath: artifactPath, testName }))
+  const indices: number[] = []
+  t.is(await executeTestsAsync(tests, ({ index }) => indices.push(index)), 0)
+  t.deepEqual(indices, [0, 1, 2, 3, 4])
+
+  const iterations: number[] = []
+  const statistics = await benchmarkTestAsync(artifactPath, testName, 5, ({ iteration }) => iterations.push(iteration))
+  t.is(statistics.runs, 5)
+  t.deepEqual(iterations, [0, 1, 2, 3, 4])
 })
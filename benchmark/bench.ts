@@ -1,19 +1,38 @@
 import { ArgumentParser } from "argparse";
 import child_process from "child_process";
-import { executeTestSync, executeTestAsync, TestResult, PerfReportConfig } from '../index.js'
+import {
+  benchmarkStatistics,
+  executeTestSync,
+  executeTestAsync,
+  latencyHistogram,
+  BenchmarkStatistics,
+  LatencyHistogram,
+  TestResult,
+  PerfReportConfig,
+  Environment,
+  IntegerFormat,
+} from '../index.js'
+import { executeTestInWorker } from '../worker.js'
 import { stringify } from 'csv-stringify/sync'
 import fs from 'fs'
 
 const artifactPath = "contracts/Avg_Unit_Test.json";
 const testName = "test_Avg_OneOperandEvenTheOtherOdd()";
+// Results are printed as JSON, which can't hold bigints.
+const options = { integerFormat: IntegerFormat.Hex };
 
 async function runExecuteTestAsync(perfReportConfig?: PerfReportConfig) {
-  const result = await executeTestAsync(artifactPath, testName, perfReportConfig);
+  const result = await executeTestAsync(artifactPath, testName, perfReportConfig, options);
   console.log(JSON.stringify(result));
 }
 
 function runExecuteTestSync(perfReportConfig?: PerfReportConfig) {
-  const result = executeTestSync(artifactPath, testName, perfReportConfig);
+  const result = executeTestSync(artifactPath, testName, perfReportConfig, options);
+  console.log(JSON.stringify(result));
+}
+
+async function runExecuteTestInWorker(perfReportConfig?: PerfReportConfig) {
+  const result = await executeTestInWorker(artifactPath, testName, perfReportConfig, options);
   console.log(JSON.stringify(result));
 }
 
@@ -41,7 +60,11 @@ function runInSubprocess(command: string, args: string[]): TestResult {
 
 interface TestOptions {
   perfReportConfig?: PerfReportConfig;
-  async: boolean
+  async: boolean;
+  // Execute the test synchronously on a Node worker thread instead of the main thread
+  worker?: boolean;
+  // Cargo feature selecting the global allocator of the Rust executor
+  allocator?: string;
 }
 
 function runNodeTest(options: TestOptions) {
@@ -54,7 +77,9 @@ function runNodeTest(options: TestOptions) {
     "benchmark/bench.ts",
   ];
 
-  if (options.async) {
+  if (options.worker) {
+    args.push("execute-test-worker");
+  } else if (options.async) {
     args.push("execute-test-async");
   } else {
     args.push("execute-test-sync");
@@ -68,6 +93,13 @@ function runNodeTest(options: TestOptions) {
     if (options.perfReportConfig.l1InstructionCacheMisses) args.push("--l1-instruction-cache-misses");
     if (options.perfReportConfig.branchMissRatio) args.push("--branch-miss-ratio");
     if (options.perfReportConfig.cpuMigrations) args.push("--cpu-migrations");
+    if (options.perfReportConfig.instructionTlbMisses) args.push("--instruction-tlb-misses");
+    if (options.perfReportConfig.dataTlbMissRatio) args.push("--data-tlb-miss-ratio");
+    if (options.perfReportConfig.pageFaults) args.push("--page-faults");
+    if (options.perfReportConfig.contextSwitches) args.push("--context-switches");
+    if (options.perfReportConfig.taskClock) args.push("--task-clock");
+    if (options.perfReportConfig.stalledCycles) args.push("--stalled-cycles");
+    if (options.perfReportConfig.refCycles) args.push("--ref-cycles");
   }
 
   return runInSubprocess(process.argv[0], args);
@@ -75,8 +107,12 @@ function runNodeTest(options: TestOptions) {
 
 function runCargoInSubProcess(options: TestOptions) {
   const args = [
-    "run", "--quiet", "--bin", "execute_test", "--release", "--"
+    "run", "--quiet", "--bin", "execute_test", "--release"
   ];
+  if (options.allocator && options.allocator !== "system") {
+    args.push("--features", `napi-rs-revm-cli/${options.allocator}`);
+  }
+  args.push("--");
 
   if (options.perfReportConfig) {
     if (options.perfReportConfig.instructions) args.push("--instructions");
@@ -86,6 +122,13 @@ function runCargoInSubProcess(options: TestOptions) {
     if (options.perfReportConfig.l1InstructionCacheMisses) args.push("--l1-instruction-cache-misses");
     if (options.perfReportConfig.branchMissRatio) args.push("--branch-miss-ratio");
     if (options.perfReportConfig.cpuMigrations) args.push("--cpu-migrations");
+    if (options.perfReportConfig.instructionTlbMisses) args.push("--instruction-tlb-misses");
+    if (options.perfReportConfig.dataTlbMissRatio) args.push("--data-tlb-miss-ratio");
+    if (options.perfReportConfig.pageFaults) args.push("--page-faults");
+    if (options.perfReportConfig.contextSwitches) args.push("--context-switches");
+    if (options.perfReportConfig.taskClock) args.push("--task-clock");
+    if (options.perfReportConfig.stalledCycles) args.push("--stalled-cycles");
+    if (options.perfReportConfig.refCycles) args.push("--ref-cycles");
   }
 
   if (options.async) {
@@ -97,69 +140,79 @@ function runCargoInSubProcess(options: TestOptions) {
   return runInSubprocess("cargo", args);
 }
 
-interface BenchmarkStats {
-  runs: number;
-  mean: number;
-  median: number;
-  min: number;
-  max: number;
-  stdDev: number;
-}
-
 interface BenchmarkResult {
   name: string;
   async: boolean;
   executor: string;
-  stats: BenchmarkStats;
+  allocator: string;
+  environment?: Environment;
+  stats: BenchmarkStatistics;
+  // Only recorded for durations
+  histogram?: LatencyHistogram;
 }
 
-function calculateStatistics(values: number[]): BenchmarkStats {
-  // Calculate statistics
-  const count = values.length;
-  const sum = values.reduce((a, b) => a + b, 0);
-  const mean = sum / count;
-  const sorted = values.sort((a, b) => a - b);
-  const median = count % 2 === 0
-    ? (sorted[count / 2 - 1] + sorted[count / 2]) / 2
-    : sorted[Math.floor(count / 2)];
-  const min = sorted[0];
-  const max = sorted[sorted.length - 1];
-
-  // Calculate standard deviation
-  const variance = values.reduce((sum, val) => sum + Math.pow(val - mean, 2), 0) / count;
-  const stdDev = Math.sqrt(variance);
-
-  return {
-    runs: count,
-    mean,
-    median,
-    min,
-    max,
-    stdDev
+interface Samples {
+  values: number[];
+  // Global allocator reported by the test results
+  allocator: string;
+  environment?: Environment;
+  // Runs that started and finished on different CPUs or counted a CPU migration
+  migratedRuns: number;
+}
+
+function calculateStatistics(name: string, samples: Samples): BenchmarkStatistics {
+  const stats = benchmarkStatistics(samples.values, samples.migratedRuns);
+  for (const warning of stats.warnings) {
+    console.warn(`${name}: ${warning.message}`);
   }
+  return stats;
 }
 
-function executeTimes(count: number, options: TestOptions, func: (options: TestOptions) => TestResult): number[] {
+function executeTimes(count: number, options: TestOptions, func: (options: TestOptions) => TestResult): Samples {
   const values = [];
+  let allocator = "";
+  let environment: Environment | undefined;
+  let migratedRuns = 0;
   for (let i = 0; i < count; i++) {
     const testResult = func(options);
+    allocator = testResult.allocator;
+    environment = testResult.environment;
+    const cpuChanged =
+      testResult.cpuBefore !== undefined && testResult.cpuAfter !== undefined && testResult.cpuBefore !== testResult.cpuAfter;
+    if (cpuChanged || Number(testResult.perfReport?.cpuMigrations ?? 0) > 0) {
+      migratedRuns++;
+    }
     if (testResult.perfReport) {
       // Push the first non-undefined perf metric we find
       const perfReport = testResult.perfReport;
       if (perfReport.instructionsPerCycle !== undefined) {
         values.push(perfReport.instructionsPerCycle)
       } else if (perfReport.instructions !== undefined) {
-        values.push(perfReport.instructions);
+        values.push(Number(perfReport.instructions));
       } else if (perfReport.lastLevelCacheHitRate !== undefined) {
         values.push(perfReport.lastLevelCacheHitRate);
       } else if (perfReport.l1DataCacheHitRate !== undefined) {
         values.push(perfReport.l1DataCacheHitRate);
       } else if (perfReport.l1InstructionCacheMisses !== undefined) {
-        values.push(perfReport.l1InstructionCacheMisses);
+        values.push(Number(perfReport.l1InstructionCacheMisses));
       } else if (perfReport.branchMissRatio !== undefined) {
         values.push(perfReport.branchMissRatio);
       } else if (perfReport.cpuMigrations !== undefined) {
-        values.push(perfReport.cpuMigrations);
+        values.push(Number(perfReport.cpuMigrations));
+      } else if (perfReport.instructionTlbMisses !== undefined) {
+        values.push(Number(perfReport.instructionTlbMisses));
+      } else if (perfReport.dataTlbMissRatio !== undefined) {
+        values.push(perfReport.dataTlbMissRatio);
+      } else if (perfReport.pageFaultsMinor !== undefined) {
+        values.push(Number(perfReport.pageFaultsMinor));
+      } else if (perfReport.contextSwitches !== undefined) {
+        values.push(Number(perfReport.contextSwitches));
+      } else if (perfReport.taskClockNs !== undefined) {
+        values.push(Number(perfReport.taskClockNs));
+      } else if (perfReport.stalledCyclesBackendRatio !== undefined) {
+        values.push(perfReport.stalledCyclesBackendRatio);
+      } else if (perfReport.cyclesPerRefCycle !== undefined) {
+        values.push(perfReport.cyclesPerRefCycle);
       } else {
         values.push(testResult.durationNs);
       }
@@ -167,10 +220,10 @@ function executeTimes(count: number, options: TestOptions, func: (options: TestO
       values.push(testResult.durationNs);
     }
   }
-  return values
+  return { values, allocator, environment, migratedRuns }
 }
 
-function runBenchmark(runs: number) {
+function runBenchmark(runs: number, allocator: string): BenchmarkResult[] {
   const defaultConfig: PerfReportConfig = {
     instructions: false,
     instructionsPerCycle: false,
@@ -179,6 +232,13 @@ function runBenchmark(runs: number) {
     l1InstructionCacheMisses: false,
     branchMissRatio: false,
     cpuMigrations: false,
+    instructionTlbMisses: false,
+    dataTlbMissRatio: false,
+    pageFaults: false,
+    contextSwitches: false,
+    taskClock: false,
+    stalledCycles: false,
+    refCycles: false,
   };
 
   const configs: Array<{ name: string, config?: PerfReportConfig }> = [
@@ -190,28 +250,52 @@ function runBenchmark(runs: number) {
     { name: "L1InstructionCacheMisses", config: { ...defaultConfig, l1InstructionCacheMisses: true } },
     { name: "BranchMissRatio", config: { ...defaultConfig, branchMissRatio: true } },
     { name: "CpuMigrations", config: { ...defaultConfig, cpuMigrations: true } },
+    { name: "InstructionTlbMisses", config: { ...defaultConfig, instructionTlbMisses: true } },
+    { name: "DataTlbMissRatio", config: { ...defaultConfig, dataTlbMissRatio: true } },
+    { name: "PageFaults", config: { ...defaultConfig, pageFaults: true } },
+    { name: "ContextSwitches", config: { ...defaultConfig, contextSwitches: true } },
+    { name: "TaskClock", config: { ...defaultConfig, taskClock: true } },
+    { name: "StalledCycles", config: { ...defaultConfig, stalledCycles: true } },
+    { name: "RefCycles", config: { ...defaultConfig, refCycles: true } },
   ];
 
   const results = [];
 
   for (const { name, config } of configs) {
     for (let async of [true, false]) {
-      const nodeStats = calculateStatistics(executeTimes(runs, { perfReportConfig: config, async }, runNodeTest));
+      const nodeSamples = executeTimes(runs, { perfReportConfig: config, async }, runNodeTest);
       results.push({
         name,
         async,
         executor: "node",
-        stats: nodeStats
+        allocator: nodeSamples.allocator,
+        environment: nodeSamples.environment,
+        stats: calculateStatistics(`${name} node`, nodeSamples),
+        histogram: config === undefined ? latencyHistogram(nodeSamples.values) : undefined,
       })
 
-      const rustStats = calculateStatistics(executeTimes(runs, { perfReportConfig: config, async }, runCargoInSubProcess));
+      const rustSamples = executeTimes(runs, { perfReportConfig: config, async, allocator }, runCargoInSubProcess);
       results.push({
         name,
         async,
         executor: "rust",
-        stats: rustStats
+        allocator: rustSamples.allocator,
+        environment: rustSamples.environment,
+        stats: calculateStatistics(`${name} rust`, rustSamples),
+        histogram: config === undefined ? latencyHistogram(rustSamples.values) : undefined,
       })
     }
+
+    const workerSamples = executeTimes(runs, { perfReportConfig: config, async: false, worker: true }, runNodeTest);
+    results.push({
+      name,
+      async: false,
+      executor: "node-worker",
+      allocator: workerSamples.allocator,
+      environment: workerSamples.environment,
+      stats: calculateStatistics(`${name} node-worker`, workerSamples),
+      histogram: config === undefined ? latencyHistogram(workerSamples.values) : undefined,
+    })
   }
 
   return results
@@ -222,29 +306,58 @@ function saveBenchmarkResultsToCsv(results: BenchmarkResult[], outputPath: strin
     name: r.name,
     async: r.async ? 'true' : 'false',
     executor: r.executor,
+    allocator: r.allocator,
     runs: r.stats.runs,
     mean: r.stats.mean,
     median: r.stats.median,
     min: r.stats.min,
     max: r.stats.max,
     stdDev: r.stats.stdDev,
+    p50: r.histogram?.p50Ns ?? '',
+    p90: r.histogram?.p90Ns ?? '',
+    p99: r.histogram?.p99Ns ?? '',
+    p999: r.histogram?.p999Ns ?? '',
+    outliers: r.stats.outliers.length,
+    warnings: r.stats.warnings.map((warning) => warning.kind).join(' '),
+    cpuModel: r.environment?.cpu.model ?? '',
+    logicalCpus: r.environment?.cpu.logicalCpus ?? '',
+    governor: r.environment?.cpu.scalingGovernors.join(' ') ?? '',
+    smt: r.environment?.cpu.smtControl ?? '',
+    turbo: r.environment?.cpu.turbo ?? '',
+    kernel: r.environment?.kernel.release ?? '',
+    rustc: r.environment?.build.rustcVersion ?? '',
+    profile: r.environment?.build.profile ?? '',
+    revm: r.environment?.build.revmVersion ?? '',
   }))
 
   const csv = stringify(csvData, {
     header: true,
-    columns: ['name', 'async', 'executor', 'runs', 'mean', 'median', 'min', 'max', 'stdDev']
+    columns: ['name', 'async', 'executor', 'allocator', 'runs', 'mean', 'median', 'min', 'max', 'stdDev', 'p50', 'p90', 'p99', 'p999', 'outliers', 'warnings', 'cpuModel', 'logicalCpus',
+      'governor', 'smt', 'turbo', 'kernel', 'rustc', 'profile', 'revm']
   })
 
   fs.writeFileSync(outputPath, csv)
   console.log(`Benchmark results saved to ${outputPath}`)
 }
 
+function saveHistogramLogs(results: BenchmarkResult[], outputDir: string) {
+  fs.mkdirSync(outputDir, { recursive: true })
+  for (const r of results) {
+    if (r.histogram) {
+      const mode = r.async ? 'async' : 'sync'
+      fs.writeFileSync(`${outputDir}/${r.name}-${r.executor}-${mode}.hlog`, r.histogram.log)
+    }
+  }
+  console.log(`Histogram logs saved to ${outputDir}`)
+}
+
 interface ParsedArguments {
   command:
     | "execute-test-sync"
     | "execute-test-async"
     | "benchmark";
   count: number;
+  allocator: string;
   instructions: boolean;
   instructions_per_cycle: boolean;
   last_level_cache_hit_rate: boolean;
@@ -252,6 +365,13 @@ interface ParsedArguments {
   l1_instruction_cache_misses: boolean;
   branch_miss_ratio: boolean;
   cpu_migrations: boolean;
+  instruction_tlb_misses: boolean;
+  data_tlb_miss_ratio: boolean;
+  page_faults: boolean;
+  context_switches: boolean;
+  task_clock: boolean;
+  stalled_cycles: boolean;
+  ref_cycles: boolean;
 }
 
 async function main() {
@@ -262,6 +382,7 @@ async function main() {
     choices: [
       "execute-test-sync",
       "execute-test-async",
+      "execute-test-worker",
       "benchmark"
     ],
   });
@@ -270,6 +391,11 @@ async function main() {
     default: 27,
     help: "Number of samples",
   });
+  parser.add_argument("--allocator", {
+    choices: ["system", "mimalloc", "jemalloc"],
+    default: "system",
+    help: "Global allocator of the Rust executor. Build the addon with the same cargo feature to compare the Node executor",
+  });
   parser.add_argument("--instructions", {
     action: "store_true",
     help: "Collect instructions",
@@ -298,6 +424,34 @@ async function main() {
     action: "store_true",
     help: "Collect CPU migrations",
   });
+  parser.add_argument("--instruction-tlb-misses", {
+    action: "store_true",
+    help: "Collect instruction TLB misses",
+  });
+  parser.add_argument("--data-tlb-miss-ratio", {
+    action: "store_true",
+    help: "Collect data TLB miss ratio",
+  });
+  parser.add_argument("--page-faults", {
+    action: "store_true",
+    help: "Collect minor and major page faults",
+  });
+  parser.add_argument("--context-switches", {
+    action: "store_true",
+    help: "Collect voluntary and involuntary context switches",
+  });
+  parser.add_argument("--task-clock", {
+    action: "store_true",
+    help: "Collect task clock",
+  });
+  parser.add_argument("--stalled-cycles", {
+    action: "store_true",
+    help: "Collect frontend and backend stalled cycle ratios",
+  });
+  parser.add_argument("--ref-cycles", {
+    action: "store_true",
+    help: "Collect reference cycles",
+  });
 
   const args: ParsedArguments = parser.parse_args();
 
@@ -308,7 +462,14 @@ async function main() {
     args.l1_data_cache_hit_rate ||
     args.l1_instruction_cache_misses ||
     args.branch_miss_ratio ||
-    args.cpu_migrations
+    args.cpu_migrations ||
+    args.instruction_tlb_misses ||
+    args.data_tlb_miss_ratio ||
+    args.page_faults ||
+    args.context_switches ||
+    args.task_clock ||
+    args.stalled_cycles ||
+    args.ref_cycles
   ) ? {
     instructions: args.instructions,
     instructionsPerCycle: args.instructions_per_cycle,
@@ -317,15 +478,25 @@ async function main() {
     l1InstructionCacheMisses: args.l1_instruction_cache_misses,
     branchMissRatio: args.branch_miss_ratio,
     cpuMigrations: args.cpu_migrations,
+    instructionTlbMisses: args.instruction_tlb_misses,
+    dataTlbMissRatio: args.data_tlb_miss_ratio,
+    pageFaults: args.page_faults,
+    contextSwitches: args.context_switches,
+    taskClock: args.task_clock,
+    stalledCycles: args.stalled_cycles,
+    refCycles: args.ref_cycles,
   } : undefined;
 
   if (args.command === "execute-test-sync") {
     runExecuteTestSync(perfReportConfig)
   } else if (args.command === "execute-test-async") {
     await runExecuteTestAsync(perfReportConfig)
+  } else if (args.command === "execute-test-worker") {
+    await runExecuteTestInWorker(perfReportConfig)
   } else if (args.command === "benchmark") {
-    const results = runBenchmark(args.count)
+    const results = runBenchmark(args.count, args.allocator)
     saveBenchmarkResultsToCsv(results, 'benchmark_results.csv')
+    saveHistogramLogs(results, 'benchmark_histograms')
   } else {
     throw new Error(`Unknown command: ${args.command}`)
   }
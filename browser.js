@@ -0,0 +1 @@
+export * from 'napi-rs-revm-wasm32-wasi'
@@ -1,6 +1,11 @@
 use clap::{Parser, Subcommand};
 use eyre::Result;
-use napi_rs_revm_core::{execute_test, PerfReportConfig, TestResult};
+use napi_rs_revm_core::{
+    check_perf_events, environment, execute_test_on, execute_test_with_options, latency_histogram,
+    perf_event_paranoid, BenchmarkStatistics, CountingMode, ExecuteTestOptions, ExecutorConfig,
+    MultiplexingPolicy, PerfReportConfig, RawEventConfig, SamplingConfig, SchedulingConfig,
+    TestResult, ThreadKind,
+};
 use std::path::PathBuf;
 
 /// Execute a Solidity test with REVM
@@ -49,31 +54,177 @@ struct Args {
     /// Collect CPU migrations
     #[arg(long, default_value = "false")]
     cpu_migrations: bool,
+
+    /// Collect instruction TLB misses
+    #[arg(long, default_value = "false")]
+    instruction_tlb_misses: bool,
+
+    /// Collect data TLB miss ratio
+    #[arg(long, default_value = "false")]
+    data_tlb_miss_ratio: bool,
+
+    /// Collect minor and major page faults
+    #[arg(long, default_value = "false")]
+    page_faults: bool,
+
+    /// Collect voluntary and involuntary context switches
+    #[arg(long, default_value = "false")]
+    context_switches: bool,
+
+    /// Collect task clock
+    #[arg(long, default_value = "false")]
+    task_clock: bool,
+
+    /// Collect frontend and backend stalled cycle ratios
+    #[arg(long, default_value = "false")]
+    stalled_cycles: bool,
+
+    /// Collect reference cycles
+    #[arg(long, default_value = "false")]
+    ref_cycles: bool,
+
+    /// Collect a raw PMU event given as `name=type:config[:config1]` in hex (repeatable)
+    #[arg(long = "raw-event")]
+    raw_events: Vec<RawEventConfig>,
+
+    /// Collect an event from the named event table, e.g. `frontend_retired.l1i_miss` (repeatable)
+    #[arg(long = "named-event", value_parser = parse_named_event)]
+    named_events: Vec<String>,
+
+    /// Split perf counters into groups of at most this many hardware events
+    #[arg(long)]
+    max_hardware_events_per_group: Option<usize>,
+
+    /// How to handle multiplexed perf counters: `scale` extrapolates them, `reject` fails
+    #[arg(long, default_value = "scale")]
+    multiplexing: MultiplexingPolicy,
+
+    /// Count perf events in `user` space, `kernel` space or `user-and-kernel`. Scheduler events and
+    /// clocks are reported with the mode they are counted in, see `effectiveCountingModes`
+    #[arg(long, default_value = "user")]
+    counting_mode: CountingMode,
+
+    /// Also count perf events while the hypervisor runs
+    #[arg(long, default_value = "false")]
+    include_hypervisor: bool,
+
+    /// Sample call stacks during the REVM transaction and write them folded to this file. Build
+    /// with `-C force-frame-pointers=yes` for complete stacks
+    #[arg(long)]
+    sample_folded: Option<PathBuf>,
+
+    /// Sample call stacks during the REVM transaction and write an SVG flamegraph to this file
+    #[arg(long)]
+    flamegraph: Option<PathBuf>,
+
+    /// Event to sample on: `cycles`, `instructions`, `cpu-clock`, `task-clock`, a named event or
+    /// `name=type:config[:config1]`
+    #[arg(long, default_value = "cycles", value_parser = SamplingConfig::parse_event)]
+    sample_event: RawEventConfig,
+
+    /// Samples per second
+    #[arg(long)]
+    sample_frequency: Option<u64>,
+
+    /// Also time the REVM transaction with the timestamp counter
+    #[arg(long, default_value = "false")]
+    tsc_timing: bool,
+
+    /// Time each phase of the test: load, decode, analyse, db, build, transact and result handling
+    #[arg(long, default_value = "false")]
+    phase_timings: bool,
+
+    /// Report allocations, RSS growth and page faults during the REVM transaction (allocations
+    /// require the counting-allocator feature)
+    #[arg(long, default_value = "false")]
+    memory_report: bool,
+
+    /// Pin the executing thread to these CPUs (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    cpu_affinity: Option<Vec<usize>>,
+
+    /// Run the executing thread under SCHED_FIFO with this priority (1-99)
+    #[arg(long)]
+    fifo_priority: Option<i32>,
+
+    /// Nice value of the executing thread
+    #[arg(long, allow_hyphen_values = true)]
+    nice: Option<i32>,
 }
 
 #[derive(Clone, Debug, Subcommand)]
 enum Command {
     ExecuteTestSync,
     ExecuteTestAsync,
+    /// Execute the test on the selected kind of thread
+    ExecuteTestOn {
+        /// Kind of thread to execute the test on
+        #[arg(long, default_value = "current-thread")]
+        thread_kind: ThreadKind,
+
+        /// Stack size in bytes of the spawned thread
+        #[arg(long)]
+        stack_size: Option<usize>,
+    },
+    /// List which perf counters work in the selected counting mode on this machine and why others
+    /// don't
+    PerfCheck,
+    /// Print the CPU, kernel and build fingerprint that is attached to every result
+    Environment,
+    /// Execute the test repeatedly on the current thread and print statistics of the durations
+    Benchmark {
+        /// Number of runs
+        #[arg(long, default_value = "27")]
+        runs: usize,
+
+        /// Write an HDR histogram of the durations in the interval log format to this file
+        #[arg(long)]
+        histogram_log: Option<PathBuf>,
+    },
+}
+
+fn parse_named_event(name: &str) -> Result<String, String> {
+    if RawEventConfig::named(name).is_some() {
+        Ok(name.to_string())
+    } else {
+        let names: Vec<_> = RawEventConfig::names().collect();
+        Err(format!("expected one of: {}", names.join(", ")))
+    }
 }
 
 fn execute_test_async(
     test_artifact_path: PathBuf,
     test_name: String,
-    perf_report_config: Option<PerfReportConfig>,
+    options: ExecuteTestOptions,
 ) -> Result<TestResult> {
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()?;
     runtime.block_on(runtime.spawn_blocking(move || {
-        execute_test(
-            test_artifact_path.as_path(),
-            &test_name,
-            perf_report_config,
-        )
+        execute_test_with_options(test_artifact_path.as_path(), &test_name, options)
     }))?
 }
 
+fn perf_check(counting_mode: CountingMode, include_hypervisor: bool) {
+    match perf_event_paranoid() {
+        Some(level) => println!("perf_event_paranoid: {level}"),
+        None => println!("perf_event_paranoid: unknown"),
+    }
+
+    let availability = check_perf_events(counting_mode, include_hypervisor);
+    let name_width = availability
+        .iter()
+        .map(|event| event.name.len())
+        .max()
+        .unwrap_or(0);
+    for event in availability {
+        match event.unavailable_reason {
+            None => println!("{:name_width$}  ok", event.name),
+            Some(reason) => println!("{:name_width$}  unavailable: {reason}", event.name),
+        }
+    }
+}
+
 fn main() -> Result<()> {
     let args = Args::parse();
 
@@ -85,32 +236,119 @@ fn main() -> Result<()> {
         l1_instruction_cache_misses: args.l1_instruction_cache_misses,
         branch_miss_ratio: args.branch_miss_ratio,
         cpu_migrations: args.cpu_migrations,
+        instruction_tlb_misses: args.instruction_tlb_misses,
+        data_tlb_miss_ratio: args.data_tlb_miss_ratio,
+        page_faults: args.page_faults,
+        context_switches: args.context_switches,
+        task_clock: args.task_clock,
+        stalled_cycles: args.stalled_cycles,
+        ref_cycles: args.ref_cycles,
+        raw_events: args.raw_events,
+        named_events: args.named_events,
+        max_hardware_events_per_group: args.max_hardware_events_per_group,
+        multiplexing: args.multiplexing,
+        counting_mode: args.counting_mode,
+        include_hypervisor: args.include_hypervisor,
     };
 
-    let perf_report_config_opt = if perf_report_config.instructions
-        || perf_report_config.instructions_per_cycle
-        || perf_report_config.last_level_cache_hit_rate
-        || perf_report_config.l1_data_cache_hit_rate
-        || perf_report_config.l1_instruction_cache_misses
-        || perf_report_config.branch_miss_ratio
-        || perf_report_config.cpu_migrations
-    {
-        Some(perf_report_config)
-    } else {
+    let perf_report_config_opt = if perf_report_config.is_empty() {
         None
+    } else {
+        Some(perf_report_config)
+    };
+
+    let scheduling_opt =
+        if args.cpu_affinity.is_some() || args.fifo_priority.is_some() || args.nice.is_some() {
+            Some(SchedulingConfig {
+                cpu_affinity: args.cpu_affinity,
+                fifo_priority: args.fifo_priority,
+                nice: args.nice,
+            })
+        } else {
+            None
+        };
+
+    let sampling_opt = match (args.sample_folded, args.flamegraph) {
+        (None, None) => None,
+        (Some(folded_path), flamegraph_path) => Some((folded_path, flamegraph_path)),
+        (None, Some(flamegraph_path)) => Some((
+            flamegraph_path.with_extension("folded"),
+            Some(flamegraph_path),
+        )),
+    }
+    .map(|(folded_path, flamegraph_path)| SamplingConfig {
+        event: args.sample_event,
+        frequency: args.sample_frequency,
+        folded_path,
+        flamegraph_path,
+    });
+
+    let options = ExecuteTestOptions {
+        perf_report_config: perf_report_config_opt,
+        scheduling: scheduling_opt,
+        sampling: sampling_opt,
+        tsc_timing: args.tsc_timing,
+        phase_timings: args.phase_timings,
+        memory_report: args.memory_report,
+        cancellation: None,
     };
 
     let test_result = match args.command {
-        Command::ExecuteTestSync => execute_test(
+        Command::ExecuteTestSync => {
+            execute_test_with_options(args.test_artifact_path.as_path(), &args.test_name, options)?
+        }
+        Command::ExecuteTestAsync => {
+            execute_test_async(args.test_artifact_path, args.test_name, options)?
+        }
+        Command::ExecuteTestOn {
+            thread_kind,
+            stack_size,
+        } => execute_test_on(
+            &ExecutorConfig {
+                thread_kind,
+                stack_size,
+            },
             args.test_artifact_path.as_path(),
             &args.test_name,
-            perf_report_config_opt,
-        )?,
-        Command::ExecuteTestAsync => execute_test_async(
-            args.test_artifact_path,
-            args.test_name,
-            perf_report_config_opt,
+            options,
         )?,
+        Command::PerfCheck => {
+            perf_check(args.counting_mode, args.include_hypervisor);
+            return Ok(());
+        }
+        Command::Environment => {
+            println!("{}", serde_json::to_string_pretty(environment())?);
+            return Ok(());
+        }
+        Command::Benchmark {
+            runs,
+            histogram_log,
+        } => {
+            let results = (0..runs)
+                .map(|_| {
+                    execute_test_with_options(
+                        args.test_artifact_path.as_path(),
+                        &args.test_name,
+                        options.clone(),
+                    )
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let statistics = BenchmarkStatistics::from_test_results(&results)?;
+            for warning in &statistics.warnings {
+                eprintln!("warning: {}", warning.message);
+            }
+            let durations: Vec<f64> = results.iter().map(|result| result.duration_ns).collect();
+            let histogram = latency_histogram(&durations, None)?;
+            if let Some(path) = histogram_log {
+                std::fs::write(path, &histogram.log)?;
+            }
+            let report = serde_json::json!({
+                "statistics": statistics,
+                "histogram": histogram,
+            });
+            println!("{report}");
+            return Ok(());
+        }
     };
 
     println!("{}", serde_json::to_string(&test_result)?);
@@ -0,0 +1,64 @@
+use std::{env, fs, path::PathBuf, process::Command};
+
+/// Records the toolchain, profile and REVM version for the environment fingerprint.
+fn main() {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|version| version.trim().to_string())
+        .unwrap_or_default();
+    println!("cargo:rustc-env=NAPI_RS_REVM_RUSTC_VERSION={rustc_version}");
+
+    for name in ["PROFILE", "OPT_LEVEL", "TARGET"] {
+        let value = env::var(name).unwrap_or_default();
+        println!("cargo:rustc-env=NAPI_RS_REVM_{name}={value}");
+    }
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(name, _)| {
+            Some(
+                name.strip_prefix("CARGO_FEATURE_")?
+                    .to_lowercase()
+                    .replace('_', "-"),
+            )
+        })
+        .collect();
+    features.sort();
+    println!(
+        "cargo:rustc-env=NAPI_RS_REVM_FEATURES={}",
+        features.join(",")
+    );
+
+    let revm_version = find_lockfile()
+        .and_then(|lockfile| {
+            println!("cargo:rerun-if-changed={}", lockfile.display());
+            fs::read_to_string(lockfile).ok()
+        })
+        .and_then(|lockfile| locked_version(&lockfile, "revm"))
+        .unwrap_or_default();
+    println!("cargo:rustc-env=NAPI_RS_REVM_REVM_VERSION={revm_version}");
+    println!("cargo:rerun-if-changed=build.rs");
+}
+
+/// The lockfile of the workspace this crate is built in, if any.
+fn find_lockfile() -> Option<PathBuf> {
+    let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR")?);
+    manifest_dir
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.is_file())
+}
+
+fn locked_version(lockfile: &str, package: &str) -> Option<String> {
+    let name_line = format!("name = \"{package}\"");
+    let mut lines = lockfile.lines();
+    lines.find(|line| *line == name_line)?;
+    let version = lines
+        .next()?
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')?;
+    Some(version.to_string())
+}
@@ -0,0 +1,67 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Global allocator the crate was built with, selected by the `mimalloc` and `jemalloc` features.
+/// If both are enabled, e.g. by `--all-features`, mimalloc takes precedence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Allocator {
+    /// The platform allocator, glibc malloc on Linux.
+    System,
+    Mimalloc,
+    Jemalloc,
+}
+
+impl Allocator {
+    #[cfg(feature = "mimalloc")]
+    pub const ACTIVE: Allocator = Allocator::Mimalloc;
+    #[cfg(all(feature = "jemalloc", not(feature = "mimalloc")))]
+    pub const ACTIVE: Allocator = Allocator::Jemalloc;
+    #[cfg(not(any(feature = "mimalloc", feature = "jemalloc")))]
+    pub const ACTIVE: Allocator = Allocator::System;
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Allocator::System => "system",
+            Allocator::Mimalloc => "mimalloc",
+            Allocator::Jemalloc => "jemalloc",
+        }
+    }
+}
+
+impl fmt::Display for Allocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(feature = "mimalloc")]
+type SelectedAllocator = mimalloc::MiMalloc;
+#[cfg(feature = "mimalloc")]
+const SELECTED_ALLOCATOR: SelectedAllocator = mimalloc::MiMalloc;
+
+#[cfg(all(feature = "jemalloc", not(feature = "mimalloc")))]
+type SelectedAllocator = tikv_jemallocator::Jemalloc;
+#[cfg(all(feature = "jemalloc", not(feature = "mimalloc")))]
+const SELECTED_ALLOCATOR: SelectedAllocator = tikv_jemallocator::Jemalloc;
+
+// Only installed as the global allocator when it's wrapped by the counting allocator.
+#[cfg(not(any(feature = "mimalloc", feature = "jemalloc")))]
+#[cfg_attr(not(feature = "counting-allocator"), allow(dead_code))]
+type SelectedAllocator = std::alloc::System;
+#[cfg(not(any(feature = "mimalloc", feature = "jemalloc")))]
+#[cfg_attr(not(feature = "counting-allocator"), allow(dead_code))]
+const SELECTED_ALLOCATOR: SelectedAllocator = std::alloc::System;
+
+/// Counts the allocations of each thread so that they can be reported per test.
+#[cfg(feature = "counting-allocator")]
+#[global_allocator]
+static GLOBAL: crate::memory::counting::CountingAllocator<SelectedAllocator> =
+    crate::memory::counting::CountingAllocator::new(SELECTED_ALLOCATOR);
+
+#[cfg(all(
+    not(feature = "counting-allocator"),
+    any(feature = "mimalloc", feature = "jemalloc")
+))]
+#[global_allocator]
+static GLOBAL: SelectedAllocator = SELECTED_ALLOCATOR;
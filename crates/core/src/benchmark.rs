@@ -0,0 +1,378 @@
+use crate::{environment, TestResult};
+use eyre::{bail, eyre, Result};
+use hdrhistogram::{
+    serialization::{
+        interval_log::{IntervalLogWriterBuilder, Tag},
+        V2DeflateSerializer,
+    },
+    Histogram,
+};
+use serde::Serialize;
+use std::{
+    fs,
+    time::{Duration, SystemTime},
+};
+
+/// Tukey's fences are this many interquartile ranges outside the quartiles.
+const TUKEY_FENCE_FACTOR: f64 = 1.5;
+
+/// Modified z-score above which a sample is an outlier (Iglewicz and Hoaglin).
+const MAD_Z_SCORE_THRESHOLD: f64 = 3.5;
+
+/// Converts a median absolute deviation to the standard deviation of a normal distribution.
+const MAD_NORMAL_CONSISTENCY: f64 = 0.6745;
+
+/// 1-minute load average per online CPU above which other processes likely disturb the run.
+const HIGH_LOAD_AVERAGE_PER_CPU: f64 = 0.5;
+
+/// Precision of the latency histogram, values are recorded with a relative error below 0.1%.
+const HISTOGRAM_SIGNIFICANT_DIGITS: u8 = 3;
+
+/// Summary of a benchmark's samples with outliers and anything that makes them unreliable.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkStatistics {
+    pub runs: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    pub std_dev: f64,
+    pub first_quartile: f64,
+    pub third_quartile: f64,
+    pub median_absolute_deviation: f64,
+    /// Samples outside Tukey's fences or with a modified z-score above 3.5.
+    pub outliers: Vec<Outlier>,
+    pub warnings: Vec<BenchmarkWarning>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outlier {
+    /// Position of the sample in the order the runs were made.
+    pub index: usize,
+    pub value: f64,
+    /// Outside `[Q1 - 1.5 IQR, Q3 + 1.5 IQR]`.
+    pub tukey: bool,
+    /// Modified z-score based on the median absolute deviation above 3.5.
+    pub mad: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkWarning {
+    pub kind: BenchmarkWarningKind,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BenchmarkWarningKind {
+    Outliers,
+    FrequencyScaling,
+    HighLoadAverage,
+    CpuMigrations,
+}
+
+impl BenchmarkStatistics {
+    /// Statistics of the REVM transaction durations. A run counts as migrated if it started and
+    /// finished on different CPUs or perf counted a CPU migration.
+    pub fn from_test_results(results: &[TestResult]) -> Result<Self> {
+        let durations: Vec<f64> = results.iter().map(|result| result.duration_ns).collect();
+        let migrated_runs = results
+            .iter()
+            .filter(|result| {
+                let cpu_changed = result
+                    .cpu_before
+                    .zip(result.cpu_after)
+                    .is_some_and(|(before, after)| before != after);
+                let migrations = result
+                    .perf_report
+                    .as_ref()
+                    .and_then(|report| report.cpu_migrations)
+                    .unwrap_or_default();
+                cpu_changed || migrations > 0
+            })
+            .count();
+        benchmark_statistics(&durations, migrated_runs)
+    }
+}
+
+/// Computes statistics of the samples of a benchmark and warns about outliers, frequency scaling,
+/// a high load average and runs that migrated between CPUs.
+pub fn benchmark_statistics(values: &[f64], migrated_runs: usize) -> Result<BenchmarkStatistics> {
+    if values.is_empty() {
+        bail!("No samples to compute statistics of");
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    let runs = values.len();
+    let mean = values.iter().sum::<f64>() / runs as f64;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / runs as f64;
+    let median = quantile(&sorted, 0.5);
+    let first_quartile = quantile(&sorted, 0.25);
+    let third_quartile = quantile(&sorted, 0.75);
+
+    let mut absolute_deviations: Vec<f64> =
+        values.iter().map(|value| (value - median).abs()).collect();
+    absolute_deviations.sort_by(f64::total_cmp);
+    let median_absolute_deviation = quantile(&absolute_deviations, 0.5);
+
+    let outliers = find_outliers(
+        values,
+        median,
+        first_quartile,
+        third_quartile,
+        median_absolute_deviation,
+    );
+
+    let mut warnings = Vec::new();
+    if !outliers.is_empty() {
+        let outlier_values: Vec<String> = outliers
+            .iter()
+            .map(|outlier| outlier.value.to_string())
+            .collect();
+        warnings.push(BenchmarkWarning {
+            kind: BenchmarkWarningKind::Outliers,
+            message: format!(
+                "{} of {runs} samples are outliers: {}",
+                outliers.len(),
+                outlier_values.join(", ")
+            ),
+        });
+    }
+    warnings.extend(environment_warnings());
+    if migrated_runs > 0 {
+        warnings.push(BenchmarkWarning {
+            kind: BenchmarkWarningKind::CpuMigrations,
+            message: format!(
+                "{migrated_runs} of {runs} runs migrated between CPUs, pin the executing thread \
+                 with a CPU affinity"
+            ),
+        });
+    }
+
+    Ok(BenchmarkStatistics {
+        runs,
+        mean,
+        median,
+        min: sorted[0],
+        max: sorted[runs - 1],
+        std_dev: variance.sqrt(),
+        first_quartile,
+        third_quartile,
+        median_absolute_deviation,
+        outliers,
+        warnings,
+    })
+}
+
+/// Quantile of sorted samples, linearly interpolated between the closest ranks.
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let rank = q * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+fn find_outliers(
+    values: &[f64],
+    median: f64,
+    first_quartile: f64,
+    third_quartile: f64,
+    median_absolute_deviation: f64,
+) -> Vec<Outlier> {
+    let interquartile_range = third_quartile - first_quartile;
+    let lower_fence = first_quartile - TUKEY_FENCE_FACTOR * interquartile_range;
+    let upper_fence = third_quartile + TUKEY_FENCE_FACTOR * interquartile_range;
+
+    values
+        .iter()
+        .enumerate()
+        .filter_map(|(index, &value)| {
+            let tukey = value < lower_fence || value > upper_fence;
+            // With more than half of the samples equal the deviation is 0 and the score undefined.
+            let mad = median_absolute_deviation > 0.0
+                && (MAD_NORMAL_CONSISTENCY * (value - median) / median_absolute_deviation).abs()
+                    > MAD_Z_SCORE_THRESHOLD;
+            (tukey || mad).then_some(Outlier {
+                index,
+                value,
+                tukey,
+                mad,
+            })
+        })
+        .collect()
+}
+
+/// Warnings about the machine rather than the samples.
+fn environment_warnings() -> Vec<BenchmarkWarning> {
+    let mut warnings = Vec::new();
+
+    let cpu = &environment().cpu;
+    let governors: Vec<&str> = cpu
+        .scaling_governors
+        .iter()
+        .map(String::as_str)
+        .filter(|governor| *governor != "performance")
+        .collect();
+    if !governors.is_empty() {
+        warnings.push(BenchmarkWarning {
+            kind: BenchmarkWarningKind::FrequencyScaling,
+            message: format!(
+                "CPU frequency scaling governor is {} instead of performance",
+                governors.join(", ")
+            ),
+        });
+    }
+
+    let threshold = (cpu.logical_cpus as f64 * HIGH_LOAD_AVERAGE_PER_CPU).max(1.0);
+    if let Some(load_average) = load_average().filter(|load_average| *load_average > threshold) {
+        warnings.push(BenchmarkWarning {
+            kind: BenchmarkWarningKind::HighLoadAverage,
+            message: format!(
+                "1-minute load average is {load_average:.2} on {} CPUs",
+                cpu.logical_cpus
+            ),
+        });
+    }
+
+    warnings
+}
+
+/// Distribution of benchmark durations recorded in an HDR histogram.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyHistogram {
+    pub count: u64,
+    pub min_ns: u64,
+    pub max_ns: u64,
+    pub p50_ns: u64,
+    pub p90_ns: u64,
+    pub p99_ns: u64,
+    pub p999_ns: u64,
+    /// The histogram in the HdrHistogram interval log format, which `HistogramLogProcessor` and
+    /// HdrHistogram plotters read.
+    pub log: String,
+}
+
+/// Records durations in nanoseconds in an HDR histogram. The optional `tag` identifies the
+/// histogram in the log and can't contain commas or whitespace.
+pub fn latency_histogram(durations_ns: &[f64], tag: Option<&str>) -> Result<LatencyHistogram> {
+    if durations_ns.is_empty() {
+        bail!("No samples to record in a histogram");
+    }
+    let tag = tag
+        .map(|tag| {
+            Tag::new(tag)
+                .ok_or_else(|| eyre!("Histogram tag {tag:?} contains a comma or whitespace"))
+        })
+        .transpose()?;
+
+    let mut histogram = Histogram::<u64>::new(HISTOGRAM_SIGNIFICANT_DIGITS)?;
+    for &duration_ns in durations_ns {
+        if !duration_ns.is_finite() || duration_ns < 0.0 {
+            bail!("Can't record {duration_ns} in a histogram of durations");
+        }
+        histogram.record(duration_ns.round() as u64)?;
+    }
+
+    let now = SystemTime::now();
+    let total = Duration::from_nanos(durations_ns.iter().sum::<f64>() as u64);
+    let mut log = Vec::new();
+    let mut serializer = V2DeflateSerializer::new();
+    IntervalLogWriterBuilder::new()
+        .add_comment("Durations in nanoseconds")
+        .with_start_time(now)
+        .with_base_time(now)
+        .begin_log_with(&mut log, &mut serializer)?
+        .write_histogram(&histogram, Duration::ZERO, total, tag)
+        .map_err(|err| eyre!("Failed to write histogram log: {err}"))?;
+
+    Ok(LatencyHistogram {
+        count: histogram.len(),
+        min_ns: histogram.min(),
+        max_ns: histogram.max(),
+        p50_ns: histogram.value_at_quantile(0.5),
+        p90_ns: histogram.value_at_quantile(0.9),
+        p99_ns: histogram.value_at_quantile(0.99),
+        p999_ns: histogram.value_at_quantile(0.999),
+        log: String::from_utf8(log)?,
+    })
+}
+
+/// 1-minute load average from `/proc/loadavg`.
+fn load_average() -> Option<f64> {
+    fs::read_to_string("/proc/loadavg")
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_benchmark_statistics_flags_outliers() -> Result<()> {
+        let mut values = vec![100.0, 101.0, 99.0, 100.5, 99.5, 100.0, 101.5, 98.5];
+        values.push(1081.0);
+
+        let statistics = benchmark_statistics(&values, 2)?;
+
+        assert_eq!(statistics.runs, 9);
+        assert_eq!(statistics.median, 100.0);
+        assert_eq!(statistics.min, 98.5);
+        assert_eq!(statistics.max, 1081.0);
+        assert_eq!(
+            statistics.outliers,
+            vec![Outlier {
+                index: 8,
+                value: 1081.0,
+                tukey: true,
+                mad: true
+            }]
+        );
+        let kinds: Vec<BenchmarkWarningKind> = statistics
+            .warnings
+            .iter()
+            .map(|warning| warning.kind)
+            .collect();
+        assert!(kinds.contains(&BenchmarkWarningKind::Outliers));
+        assert!(kinds.contains(&BenchmarkWarningKind::CpuMigrations));
+        Ok(())
+    }
+
+    #[test]
+    fn test_latency_histogram() -> Result<()> {
+        let durations: Vec<f64> = (1..=1000).map(|value| value as f64 * 1000.0).collect();
+
+        let histogram = latency_histogram(&durations, Some("sync"))?;
+
+        assert_eq!(histogram.count, 1000);
+        assert_eq!(histogram.min_ns, 1000);
+        // Values are only exact to 3 significant digits.
+        assert!((500_000..=501_000).contains(&histogram.p50_ns));
+        assert!((990_000..=991_000).contains(&histogram.p99_ns));
+        assert!(histogram.p999_ns <= histogram.max_ns);
+        assert!(histogram.log.contains("Tag=sync,"));
+        assert!(latency_histogram(&durations, Some("with space")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quantile_interpolates() {
+        let sorted = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(quantile(&sorted, 0.5), 2.5);
+        assert_eq!(quantile(&sorted, 0.25), 1.75);
+        assert_eq!(quantile(&sorted, 1.0), 4.0);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{ErrorPhase, TestError};
+use revm::{interpreter::Interpreter, Inspector};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Shared flag that cancels a test execution which hasn't started yet and interrupts one in
+/// progress at the next instruction.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels every execution holding a clone of this token. Can be called from any thread.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Fails with [`TestError::Aborted`] if the token has been cancelled.
+    pub fn check(&self, phase: ErrorPhase) -> Result<(), TestError> {
+        if self.is_cancelled() {
+            Err(TestError::Aborted { phase })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Halts the interpreter before the next instruction once the token has been cancelled.
+///
+/// The halt is an out of gas error, because revm panics on `FatalExternalError`. Enclosing frames
+/// are halted at their next instruction as well, and the outcome is then replaced by [`TestError::Aborted`].
+pub(crate) struct CancellationInspector {
+    token: CancellationToken,
+}
+
+impl CancellationInspector {
+    pub(crate) fn new(token: CancellationToken) -> Self {
+        Self { token }
+    }
+}
+
+impl<CTX> Inspector<CTX> for CancellationInspector {
+    #[inline]
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut CTX) {
+        if self.token.is_cancelled() {
+            interp.halt_oog();
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use serde::Serialize;
+use std::{collections::BTreeSet, fs, path::Path, sync::OnceLock};
+#[cfg(target_os = "linux")]
+use std::{ffi::CStr, mem};
+
+const SYSFS_CPU: &str = "/sys/devices/system/cpu";
+
+/// Description of the machine and build that produced a result, so that results can be compared
+/// across machines.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Environment {
+    pub cpu: CpuEnvironment,
+    pub kernel: KernelEnvironment,
+    pub build: BuildEnvironment,
+}
+
+/// CPU model and frequency scaling, from `/proc/cpuinfo` and `/sys/devices/system/cpu`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CpuEnvironment {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub microcode: Option<String>,
+    /// Online logical CPUs.
+    pub logical_cpus: u32,
+    /// Distinct cpufreq governors of the online CPUs, empty if cpufreq isn't available.
+    pub scaling_governors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scaling_driver: Option<String>,
+    /// Whether sibling hyperthreads are online.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smt_active: Option<bool>,
+    /// `on`, `off`, `forceoff`, `notsupported` or `notimplemented`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smt_control: Option<String>,
+    /// Whether turbo boost is enabled, from `intel_pstate/no_turbo` or `cpufreq/boost`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub turbo: Option<bool>,
+}
+
+/// Kernel fields of `uname`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KernelEnvironment {
+    pub sysname: String,
+    pub release: String,
+    pub version: String,
+    pub machine: String,
+}
+
+/// Build-time metadata of this crate.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildEnvironment {
+    pub crate_version: String,
+    pub rustc_version: String,
+    /// Cargo profile, `debug` or `release`.
+    pub profile: String,
+    pub opt_level: String,
+    pub target: String,
+    pub revm_version: String,
+    /// Enabled cargo features of this crate.
+    pub features: Vec<String>,
+}
+
+/// Environment fingerprint of the current process, collected once.
+pub fn environment() -> &'static Environment {
+    static ENVIRONMENT: OnceLock<Environment> = OnceLock::new();
+    ENVIRONMENT.get_or_init(|| Environment {
+        cpu: cpu_environment(),
+        kernel: kernel_environment(),
+        build: build_environment(),
+    })
+}
+
+fn cpu_environment() -> CpuEnvironment {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+    let cpus = Path::new(SYSFS_CPU);
+
+    let scaling_governors: BTreeSet<String> = online_cpus()
+        .iter()
+        .filter_map(|cpu| read_sysfs(&cpus.join(format!("cpu{cpu}/cpufreq/scaling_governor"))))
+        .collect();
+
+    let turbo = read_sysfs(&cpus.join("intel_pstate/no_turbo"))
+        .map(|no_turbo| no_turbo == "0")
+        .or_else(|| read_sysfs(&cpus.join("cpufreq/boost")).map(|boost| boost == "1"));
+
+    CpuEnvironment {
+        model: cpuinfo_field(&cpuinfo, "model name"),
+        vendor: cpuinfo_field(&cpuinfo, "vendor_id"),
+        microcode: cpuinfo_field(&cpuinfo, "microcode"),
+        logical_cpus: logical_cpus(),
+        scaling_governors: scaling_governors.into_iter().collect(),
+        scaling_driver: read_sysfs(&cpus.join("cpu0/cpufreq/scaling_driver")),
+        smt_active: read_sysfs(&cpus.join("smt/active")).map(|active| active == "1"),
+        smt_control: read_sysfs(&cpus.join("smt/control")),
+        turbo,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn logical_cpus() -> u32 {
+    // SAFETY: `sysconf` has no preconditions.
+    unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) }.max(1) as u32
+}
+
+#[cfg(not(target_os = "linux"))]
+fn logical_cpus() -> u32 {
+    std::thread::available_parallelism().map_or(1, |cpus| cpus.get() as u32)
+}
+
+/// First value of `field` in `/proc/cpuinfo`, which repeats the fields for every CPU.
+fn cpuinfo_field(cpuinfo: &str, field: &str) -> Option<String> {
+    cpuinfo.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == field).then(|| value.trim().to_string())
+    })
+}
+
+/// Parses `/sys/devices/system/cpu/online`, a list of ranges such as `0-3,8-11`.
+fn online_cpus() -> Vec<u32> {
+    let online = read_sysfs(&Path::new(SYSFS_CPU).join("online")).unwrap_or_default();
+    parse_cpu_list(&online)
+}
+
+fn parse_cpu_list(list: &str) -> Vec<u32> {
+    list.split(',')
+        .filter_map(|range| match range.split_once('-') {
+            Some((first, last)) => Some(first.trim().parse().ok()?..=last.trim().parse().ok()?),
+            None => {
+                let cpu = range.trim().parse().ok()?;
+                Some(cpu..=cpu)
+            }
+        })
+        .flatten()
+        .collect()
+}
+
+fn read_sysfs(path: &Path) -> Option<String> {
+    Some(fs::read_to_string(path).ok()?.trim().to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn kernel_environment() -> KernelEnvironment {
+    // SAFETY: `utsname` is plain data for which all zeroes is a valid value and the pointer is
+    // valid for writes. `uname` NUL-terminates every field.
+    let mut utsname: libc::utsname = unsafe { mem::zeroed() };
+    let succeeded = unsafe { libc::uname(&mut utsname) } == 0;
+    let field = |field: &[libc::c_char]| {
+        if !succeeded {
+            return String::new();
+        }
+        unsafe { CStr::from_ptr(field.as_ptr()) }
+            .to_string_lossy()
+            .into_owned()
+    };
+    KernelEnvironment {
+        sysname: field(&utsname.sysname),
+        release: field(&utsname.release),
+        version: field(&utsname.version),
+        machine: field(&utsname.machine),
+    }
+}
+
+/// Other targets have no `uname`, so only the OS and architecture are known.
+#[cfg(not(target_os = "linux"))]
+fn kernel_environment() -> KernelEnvironment {
+    KernelEnvironment {
+        sysname: std::env::consts::OS.to_string(),
+        release: String::new(),
+        version: String::new(),
+        machine: std::env::consts::ARCH.to_string(),
+    }
+}
+
+fn build_environment() -> BuildEnvironment {
+    let features = env!("NAPI_RS_REVM_FEATURES");
+    BuildEnvironment {
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        rustc_version: env!("NAPI_RS_REVM_RUSTC_VERSION").to_string(),
+        profile: env!("NAPI_RS_REVM_PROFILE").to_string(),
+        opt_level: env!("NAPI_RS_REVM_OPT_LEVEL").to_string(),
+        target: env!("NAPI_RS_REVM_TARGET").to_string(),
+        revm_version: env!("NAPI_RS_REVM_REVM_VERSION").to_string(),
+        features: features
+            .split(',')
+            .filter(|feature| !feature.is_empty())
+            .map(str::to_string)
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("0"), vec![0]);
+        assert_eq!(parse_cpu_list("0-3,8,10-11\n"), vec![0, 1, 2, 3, 8, 10, 11]);
+        assert!(parse_cpu_list("").is_empty());
+    }
+
+    #[test]
+    fn test_environment() {
+        let environment = environment();
+        assert!(environment.cpu.logical_cpus >= 1);
+        #[cfg(target_os = "linux")]
+        assert_eq!(environment.kernel.sysname, "Linux");
+        assert!(environment.build.rustc_version.starts_with("rustc "));
+        assert!(!environment.build.revm_version.is_empty());
+    }
+}
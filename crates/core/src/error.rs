@@ -0,0 +1,184 @@
+use revm::primitives::Bytes;
+use serde::Serialize;
+use std::{fmt, io, path::PathBuf};
+
+/// Phase of a test execution that an error occurred in, see [`PhaseTimings`](crate::PhaseTimings).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorPhase {
+    Load,
+    Decode,
+    Analyse,
+    Build,
+    /// Scheduling and the instrumentation around the REVM transaction.
+    Setup,
+    Transact,
+    ResultHandling,
+}
+
+/// Stable identifier of a [`TestError`] that callers can match on instead of the message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    ArtifactNotFound,
+    ArtifactUnreadable,
+    InvalidArtifact,
+    InvalidBytecode,
+    UnknownTest,
+    SchedulingFailed,
+    PerfEventsUnavailable,
+    InstrumentationFailed,
+    EvmFailed,
+    TestReverted,
+    TestHalted,
+    Aborted,
+}
+
+/// Failure of a test execution. Returned inside an `eyre::Report`, from which it can be retrieved
+/// with `downcast_ref`.
+#[derive(Debug)]
+pub enum TestError {
+    ArtifactNotFound {
+        path: PathBuf,
+    },
+    ArtifactUnreadable {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// The artifact isn't valid JSON or misses a field.
+    InvalidArtifact {
+        /// Not set for an artifact that was passed in memory.
+        path: Option<PathBuf>,
+        source: serde_json::Error,
+    },
+    /// The bytecode isn't valid hex or fails the jump destination analysis.
+    InvalidBytecode {
+        message: String,
+    },
+    /// The artifact has no method with the test's signature.
+    UnknownTest {
+        test_name: String,
+    },
+    /// The CPU affinity or scheduling policy couldn't be applied.
+    SchedulingFailed(eyre::Report),
+    /// Perf counters couldn't be opened or read, e.g. due to `perf_event_paranoid`.
+    PerfEventsUnavailable {
+        phase: ErrorPhase,
+        source: eyre::Report,
+    },
+    /// The sampler, timestamp counter or memory tracker failed.
+    InstrumentationFailed {
+        phase: ErrorPhase,
+        source: eyre::Report,
+    },
+    /// REVM rejected the transaction or failed to access the database.
+    EvmFailed {
+        message: String,
+    },
+    TestReverted {
+        /// Message of an `Error(string)` or `Panic(uint256)` revert.
+        reason: Option<String>,
+        output: Bytes,
+        gas_used: u64,
+    },
+    /// The test halted exceptionally, e.g. out of gas.
+    TestHalted {
+        reason: String,
+        gas_used: u64,
+    },
+    /// Cancelled through its [`CancellationToken`](crate::CancellationToken).
+    Aborted {
+        phase: ErrorPhase,
+    },
+}
+
+impl TestError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            TestError::ArtifactNotFound { .. } => ErrorCode::ArtifactNotFound,
+            TestError::ArtifactUnreadable { .. } => ErrorCode::ArtifactUnreadable,
+            TestError::InvalidArtifact { .. } => ErrorCode::InvalidArtifact,
+            TestError::InvalidBytecode { .. } => ErrorCode::InvalidBytecode,
+            TestError::UnknownTest { .. } => ErrorCode::UnknownTest,
+            TestError::SchedulingFailed(_) => ErrorCode::SchedulingFailed,
+            TestError::PerfEventsUnavailable { .. } => ErrorCode::PerfEventsUnavailable,
+            TestError::InstrumentationFailed { .. } => ErrorCode::InstrumentationFailed,
+            TestError::EvmFailed { .. } => ErrorCode::EvmFailed,
+            TestError::TestReverted { .. } => ErrorCode::TestReverted,
+            TestError::TestHalted { .. } => ErrorCode::TestHalted,
+            TestError::Aborted { .. } => ErrorCode::Aborted,
+        }
+    }
+
+    pub fn phase(&self) -> ErrorPhase {
+        match self {
+            TestError::ArtifactNotFound { .. }
+            | TestError::ArtifactUnreadable { .. }
+            | TestError::InvalidArtifact { .. } => ErrorPhase::Load,
+            TestError::InvalidBytecode { .. } => ErrorPhase::Decode,
+            TestError::UnknownTest { .. } => ErrorPhase::Build,
+            TestError::SchedulingFailed(_) => ErrorPhase::Setup,
+            TestError::PerfEventsUnavailable { phase, .. }
+            | TestError::InstrumentationFailed { phase, .. }
+            | TestError::Aborted { phase } => *phase,
+            TestError::EvmFailed { .. } => ErrorPhase::Transact,
+            TestError::TestReverted { .. } | TestError::TestHalted { .. } => {
+                ErrorPhase::ResultHandling
+            }
+        }
+    }
+}
+
+impl fmt::Display for TestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TestError::ArtifactNotFound { path } => {
+                write!(f, "Artifact {} doesn't exist", path.display())
+            }
+            TestError::ArtifactUnreadable { path, source } => {
+                write!(f, "Failed to read artifact {}: {source}", path.display())
+            }
+            TestError::InvalidArtifact {
+                path: Some(path),
+                source,
+            } => write!(f, "Invalid artifact {}: {source}", path.display()),
+            TestError::InvalidArtifact { path: None, source } => {
+                write!(f, "Invalid artifact: {source}")
+            }
+            TestError::InvalidBytecode { message } => write!(f, "Invalid bytecode: {message}"),
+            TestError::UnknownTest { test_name } => {
+                write!(f, "Artifact has no test function {test_name}")
+            }
+            TestError::SchedulingFailed(source) => {
+                write!(f, "Failed to apply scheduling: {source}")
+            }
+            TestError::PerfEventsUnavailable { source, .. } => {
+                write!(f, "Perf events unavailable: {source}")
+            }
+            TestError::InstrumentationFailed { source, .. } => {
+                write!(f, "Instrumentation failed: {source}")
+            }
+            TestError::EvmFailed { message } => write!(f, "REVM failed: {message}"),
+            TestError::TestReverted {
+                reason: Some(reason),
+                ..
+            } => write!(f, "Test function reverted: {reason}"),
+            TestError::TestReverted { reason: None, .. } => f.write_str("Test function reverted"),
+            TestError::TestHalted { reason, .. } => write!(f, "Test function halted: {reason}"),
+            TestError::Aborted { .. } => f.write_str("Test execution was aborted"),
+        }
+    }
+}
+
+impl std::error::Error for TestError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TestError::ArtifactUnreadable { source, .. } => Some(source),
+            TestError::InvalidArtifact { source, .. } => Some(source),
+            TestError::SchedulingFailed(source)
+            | TestError::PerfEventsUnavailable { source, .. }
+            | TestError::InstrumentationFailed { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{execute_test_with_options, ExecuteTestOptions, TestResult};
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    str::FromStr,
+    sync::{mpsc, Mutex, OnceLock},
+    thread,
+};
+
+/// The kind of thread that a test is executed on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThreadKind {
+    /// The caller's thread.
+    #[default]
+    CurrentThread,
+    /// A freshly spawned `std::thread`.
+    SpawnedThread,
+    /// A thread from the tokio blocking pool (`spawn_blocking`).
+    TokioBlocking,
+    /// A tokio runtime worker thread.
+    TokioWorker,
+    /// A thread from a rayon thread pool.
+    Rayon,
+    /// A long-lived worker thread that is reused between executions.
+    DedicatedWorker,
+}
+
+impl ThreadKind {
+    pub const ALL: [ThreadKind; 6] = [
+        ThreadKind::CurrentThread,
+        ThreadKind::SpawnedThread,
+        ThreadKind::TokioBlocking,
+        ThreadKind::TokioWorker,
+        ThreadKind::Rayon,
+        ThreadKind::DedicatedWorker,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThreadKind::CurrentThread => "current-thread",
+            ThreadKind::SpawnedThread => "spawned-thread",
+            ThreadKind::TokioBlocking => "tokio-blocking",
+            ThreadKind::TokioWorker => "tokio-worker",
+            ThreadKind::Rayon => "rayon",
+            ThreadKind::DedicatedWorker => "dedicated-worker",
+        }
+    }
+}
+
+impl fmt::Display for ThreadKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ThreadKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ThreadKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| {
+                let expected: Vec<_> = ThreadKind::ALL.iter().map(ThreadKind::as_str).collect();
+                format!(
+                    "unknown thread kind `{s}`, expected one of: {}",
+                    expected.join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExecutorConfig {
+    pub thread_kind: ThreadKind,
+    /// Stack size in bytes for [`ThreadKind::SpawnedThread`]. Uses the Rust default if not set.
+    pub stack_size: Option<usize>,
+}
+
+/// Execute a Solidity test with REVM on the thread selected by `executor` and block until it
+/// completes.
+///
+/// The returned result records the thread kind it was executed on.
+pub fn execute_test_on(
+    executor: &ExecutorConfig,
+    test_artifact_path: &Path,
+    test_name: &str,
+    options: ExecuteTestOptions,
+) -> Result<TestResult> {
+    let test_artifact_path = test_artifact_path.to_path_buf();
+    let test_name = test_name.to_string();
+    let job = move || execute_test_with_options(test_artifact_path.as_path(), &test_name, options);
+
+    let mut test_result = match executor.thread_kind {
+        ThreadKind::CurrentThread => job(),
+        ThreadKind::SpawnedThread => {
+            let mut builder = thread::Builder::new().name("revm-spawned".to_string());
+            if let Some(stack_size) = executor.stack_size {
+                builder = builder.stack_size(stack_size);
+            }
+            builder
+                .spawn(job)?
+                .join()
+                .map_err(|_| eyre!("Spawned test thread panicked"))?
+        }
+        ThreadKind::TokioBlocking => {
+            let (tx, rx) = mpsc::sync_channel(1);
+            tokio_runtime()?.spawn_blocking(move || {
+                let _ = tx.send(job());
+            });
+            receive(rx)?
+        }
+        ThreadKind::TokioWorker => {
+            let (tx, rx) = mpsc::sync_channel(1);
+            tokio_runtime()?.spawn(async move {
+                let _ = tx.send(job());
+            });
+            receive(rx)?
+        }
+        ThreadKind::Rayon => {
+            let (tx, rx) = mpsc::sync_channel(1);
+            rayon_pool()?.spawn(move || {
+                let _ = tx.send(job());
+            });
+            receive(rx)?
+        }
+        ThreadKind::DedicatedWorker => {
+            let (tx, rx) = mpsc::sync_channel(1);
+            DEDICATED_WORKER.run(Box::new(move || {
+                let _ = tx.send(job());
+            }))?;
+            receive(rx)?
+        }
+    }?;
+
+    test_result.thread_kind = Some(executor.thread_kind);
+    Ok(test_result)
+}
+
+fn receive<T>(rx: mpsc::Receiver<T>) -> Result<T> {
+    rx.recv()
+        .map_err(|_| eyre!("Executor dropped the test before it completed"))
+}
+
+/// The runtime is built once per process. A failed build is kept as well, so that it's reported
+/// to every caller instead of being retried.
+fn tokio_runtime() -> Result<&'static tokio::runtime::Runtime> {
+    static RUNTIME: OnceLock<Result<tokio::runtime::Runtime, String>> = OnceLock::new();
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .thread_name("revm-tokio")
+                .enable_all()
+                .build()
+                .map_err(|err| format!("Failed to build tokio runtime: {err}"))
+        })
+        .as_ref()
+        .map_err(|err| eyre!("{err}"))
+}
+
+fn rayon_pool() -> Result<&'static rayon::ThreadPool> {
+    static POOL: OnceLock<Result<rayon::ThreadPool, String>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        rayon::ThreadPoolBuilder::new()
+            .thread_name(|index| format!("revm-rayon-{index}"))
+            .build()
+            .map_err(|err| format!("Failed to build rayon thread pool: {err}"))
+    })
+    .as_ref()
+    .map_err(|err| eyre!("{err}"))
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+static DEDICATED_WORKER: DedicatedWorker = DedicatedWorker::new();
+
+/// A long-lived thread that runs jobs one after another. It's spawned on first use.
+struct DedicatedWorker {
+    sender: OnceLock<Result<Mutex<mpsc::Sender<Job>>, String>>,
+}
+
+impl DedicatedWorker {
+    const fn new() -> Self {
+        Self {
+            sender: OnceLock::new(),
+        }
+    }
+
+    fn run(&self, job: Job) -> Result<()> {
+        self.sender
+            .get_or_init(|| {
+                let (tx, rx) = mpsc::channel::<Job>();
+                thread::Builder::new()
+                    .name("revm-dedicated".to_string())
+                    .spawn(move || {
+                        for job in rx {
+                            // A panicking test must not take the worker down with it. Its result
+                            // sender is dropped, which the caller reports as an error.
+                            let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                        }
+                    })
+                    .map(|_| Mutex::new(tx))
+                    .map_err(|err| format!("Failed to spawn dedicated worker thread: {err}"))
+            })
+            .as_ref()
+            .map_err(|err| eyre!("{err}"))?
+            .lock()
+            .map_err(|_| eyre!("Dedicated worker lock poisoned"))?
+            .send(job)
+            .map_err(|_| eyre!("Dedicated worker has shut down"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const TEST_ARTIFACT: &str = "../../contracts/Avg_Unit_Test.json";
+    const TEST_NAME: &str = "test_Avg_OneOperandEvenTheOtherOdd()";
+
+    #[test]
+    fn test_execute_test_on_every_thread_kind() -> Result<()> {
+        let artifact_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(TEST_ARTIFACT);
+
+        for thread_kind in ThreadKind::ALL {
+            let executor = ExecutorConfig {
+                thread_kind,
+                stack_size: Some(8 * 1024 * 1024),
+            };
+            let test_result = execute_test_on(
+                &executor,
+                &artifact_path,
+                TEST_NAME,
+                ExecuteTestOptions::default(),
+            )?;
+
+            assert!(test_result.duration_ns > 0.0);
+            assert_eq!(test_result.thread_kind, Some(thread_kind));
+            assert_eq!(thread_kind.as_str().parse::<ThreadKind>(), Ok(thread_kind));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_dedicated_worker_survives_panic() -> Result<()> {
+        let worker = DedicatedWorker::new();
+        let (tx, rx) = mpsc::sync_channel::<()>(1);
+        worker.run(Box::new(move || {
+            let _tx = tx;
+            panic!("test panic");
+        }))?;
+        assert!(receive(rx).is_err());
+
+        let (tx, rx) = mpsc::sync_channel(1);
+        worker.run(Box::new(move || {
+            let _ = tx.send(thread::current().name().map(str::to_string));
+        }))?;
+        assert_eq!(receive(rx)?.as_deref(), Some("revm-dedicated"));
+        Ok(())
+    }
+}
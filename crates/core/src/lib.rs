@@ -1,92 +1,73 @@
 #![deny(clippy::all)]
-
 // Using core::intrinsics (nightly only)
-#![feature(core_intrinsics)]
+#![cfg_attr(feature = "prefetch", feature(core_intrinsics))]
+#![cfg_attr(feature = "prefetch", allow(internal_features))]
+#[cfg(feature = "prefetch")]
 use core::intrinsics::prefetch_read_instruction;
 
 use eyre::{eyre, Result};
-use num_traits::FromPrimitive;
-use perf_event::events as perf_events;
-use revm::{bytecode::Bytecode, context::{BlockEnv, CfgEnv, Context, TxEnv}, database::InMemoryDB, handler::{ExecuteEvm, MainBuilder, MainContext}, primitives::{address, keccak256, Address, Bytes, TxKind, U256}, state::AccountInfo, Journal, MainnetEvm};
+use revm::{
+    bytecode::Bytecode,
+    context::{result::ExecutionResult, BlockEnv, CfgEnv, Context, TxEnv},
+    context_interface::result::ExecResultAndState,
+    database::InMemoryDB,
+    handler::{ExecuteEvm, MainBuilder, MainContext},
+    primitives::{address, keccak256, TxKind},
+    state::AccountInfo,
+    InspectEvm, Journal, MainnetEvm,
+};
 use serde::{Deserialize, Serialize};
-use std::{fs, path::Path, time::Instant};
-use revm::context::result::ExecutionResult;
-use revm::context_interface::result::ExecResultAndState;
-
-#[derive(Clone, Debug, Default)]
-struct PerfEventConfig {
-    cycles: bool,
-    instructions: bool,
-    last_level_cache_references: bool,
-    last_level_cache_misses: bool,
-    l1_data_cache_reads: bool,
-    l1_data_cache_misses: bool,
-    l1_instruction_cache_misses: bool,
-    branch_instructions: bool,
-    branch_misses: bool,
-    cpu_migrations: bool,
-}
-
-#[derive(Clone, Debug, Default)]
-pub struct PerfReportConfig {
-    pub instructions: bool,
-    pub instructions_per_cycle: bool,
-    pub last_level_cache_hit_rate: bool,
-    pub l1_data_cache_hit_rate: bool,
-    pub l1_instruction_cache_misses: bool,
-    pub branch_miss_ratio: bool,
-    pub cpu_migrations: bool,
-}
-
-impl From<PerfReportConfig> for PerfEventConfig {
-    fn from(value: PerfReportConfig) -> Self {
-        let PerfReportConfig {
-            instructions,
-            instructions_per_cycle,
-            last_level_cache_hit_rate,
-            l1_data_cache_hit_rate,
-            l1_instruction_cache_misses,
-            branch_miss_ratio,
-            cpu_migrations,
-        } = value;
-
-        let mut config = PerfEventConfig::default();
-
-        if instructions {
-            config.instructions = true;
-        }
-
-        if instructions_per_cycle {
-            config.cycles = true;
-            config.instructions = true;
-        }
-
-        if last_level_cache_hit_rate {
-            config.last_level_cache_references = true;
-            config.last_level_cache_misses = true;
-        }
-
-        if l1_data_cache_hit_rate {
-            config.l1_data_cache_reads = true;
-            config.l1_data_cache_misses = true;
-        }
-
-        if l1_instruction_cache_misses {
-            config.l1_instruction_cache_misses = true;
-        }
-
-        if branch_miss_ratio {
-            config.branch_instructions = true;
-            config.branch_misses = true;
-        }
-
-        if cpu_migrations {
-            config.cpu_migrations = true;
-        }
-
-        config
-    }
-}
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+mod allocator;
+mod benchmark;
+mod cancellation;
+mod environment;
+mod error;
+mod executor;
+mod memory;
+mod perf;
+mod profiler;
+mod scheduling;
+mod session;
+mod tsc;
+mod worker_pool;
+
+pub use allocator::Allocator;
+pub use benchmark::{
+    benchmark_statistics, latency_histogram, BenchmarkStatistics, BenchmarkWarning,
+    BenchmarkWarningKind, LatencyHistogram, Outlier,
+};
+use cancellation::CancellationInspector;
+pub use cancellation::CancellationToken;
+pub use environment::{
+    environment, BuildEnvironment, CpuEnvironment, Environment, KernelEnvironment,
+};
+pub use error::{ErrorCode, ErrorPhase, TestError};
+pub use executor::{execute_test_on, ExecutorConfig, ThreadKind};
+use memory::MemoryTracker;
+pub use memory::{AllocationStats, MemoryReport};
+pub use perf::{
+    check_perf_events, parse_hex, perf_event_paranoid, CountingMode, MultiplexingPolicy,
+    PerfEventAvailability, PerfReport, PerfReportConfig, RawEventConfig,
+    DEFAULT_MAX_HARDWARE_EVENTS_PER_GROUP, PERF_TYPE_RAW,
+};
+use perf::{PerfEventCollector, PerfEventConfig};
+use profiler::Sampler;
+pub use profiler::{ProfileReport, SamplingConfig, DEFAULT_SAMPLING_FREQUENCY};
+pub use revm::primitives::{Address, Bytes, Log, U256};
+use scheduling::SchedulingGuard;
+pub use scheduling::{check_cpus_allowed, set_thread_affinity, SchedulingConfig};
+pub use session::{
+    AccountState, CallOutcome, CallRequest, CallStatus, EvmSession, DEFAULT_GAS_LIMIT,
+};
+pub use tsc::TscTiming;
+pub use worker_pool::{TestCase, WarmUpConfig, WorkerPool, WorkerPoolConfig};
 
 #[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -95,6 +76,97 @@ pub struct TestResult {
     pub duration_ns: f64,
     /// Optional report generated from perf events.
     pub perf_report: Option<PerfReport>,
+    /// Kind of thread the test was executed on, if it was run through [`execute_test_on`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_kind: Option<ThreadKind>,
+    /// Name of the thread the test was executed on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_name: Option<String>,
+    /// CPU the test was running on right before the REVM transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_before: Option<u32>,
+    /// CPU the test was running on right after the REVM transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_after: Option<u32>,
+    /// Sampling profile of the REVM transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<ProfileReport>,
+    /// Execution time of the REVM transaction measured with the timestamp counter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tsc_timing: Option<TscTiming>,
+    /// Time spent in each phase of the test, from loading the artifact to handling the result.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase_timings: Option<PhaseTimings>,
+    /// Allocations, RSS growth and page faults during the REVM transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryReport>,
+    /// Global allocator the crate was built with.
+    pub allocator: Allocator,
+    /// Machine and build the test was executed with.
+    pub environment: Environment,
+}
+
+/// Wall-clock time of each phase of [`execute_test_with_options`], measured with `Instant`.
+///
+/// Setting up perf counters, the sampler and the timestamp counter isn't part of any phase.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhaseTimings {
+    /// Reading the artifact file and parsing its JSON.
+    pub load_ns: f64,
+    /// Hex decoding the deployed bytecode.
+    pub decode_ns: f64,
+    /// Jump destination analysis in `Bytecode::new_raw_checked`.
+    pub analyse_ns: f64,
+    /// Hashing the bytecode and inserting the contract account into the database.
+    pub db_ns: f64,
+    /// Computing the selector and building the EVM and the transaction.
+    pub build_ns: f64,
+    /// The REVM transaction, same as [`TestResult::duration_ns`].
+    pub transact_ns: f64,
+    /// Checking the transaction result and dropping it together with the EVM.
+    pub result_handling_ns: f64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExecuteTestOptions {
+    /// Collect a perf event report for the REVM transaction.
+    pub perf_report_config: Option<PerfReportConfig>,
+    /// CPU affinity and scheduling policy for the executing thread while the test runs.
+    pub scheduling: Option<SchedulingConfig>,
+    /// Sample call stacks during the REVM transaction and write a flamegraph.
+    pub sampling: Option<SamplingConfig>,
+    /// Also time the REVM transaction with the timestamp counter, which has less overhead and a
+    /// finer resolution than `Instant`.
+    pub tsc_timing: bool,
+    /// Time each phase of the test separately, see [`PhaseTimings`].
+    pub phase_timings: bool,
+    /// Report allocations, RSS growth and page faults during the REVM transaction. Allocations
+    /// are only counted when built with the `counting-allocator` feature.
+    pub memory_report: bool,
+    /// Fail with [`TestError::Aborted`] if the token is cancelled before the test starts or while it runs.
+    /// The REVM transaction is then inspected, which slows it down.
+    pub cancellation: Option<CancellationToken>,
+}
+
+/// Compiled test contract, either on disk or already in memory.
+#[derive(Clone, Debug)]
+pub enum TestArtifact {
+    /// JSON artifact as written by `forge build`.
+    Path(PathBuf),
+    /// Parsed JSON artifact.
+    Json(serde_json::Value),
+    /// Deployed bytecode of the contract, without an ABI to check the test name against.
+    Bytecode(Bytes),
+}
+
+impl TestArtifact {
+    /// Parses the contents of a JSON artifact.
+    pub fn from_json_str(json: &str) -> Result<Self, TestError> {
+        serde_json::from_str(json)
+            .map(TestArtifact::Json)
+            .map_err(|source| TestError::InvalidArtifact { path: None, source })
+    }
 }
 
 type TestContext = Context<BlockEnv, TxEnv, CfgEnv, InMemoryDB, Journal<InMemoryDB>, ()>;
@@ -105,19 +177,107 @@ pub fn execute_test(
     test_name: &str,
     perf_report_config: Option<PerfReportConfig>,
 ) -> Result<TestResult> {
+    execute_test_with_options(
+        test_artifact_path,
+        test_name,
+        ExecuteTestOptions {
+            perf_report_config,
+            ..ExecuteTestOptions::default()
+        },
+    )
+}
+
+/// Execute a Solidity test with REVM on the calling thread with the given options.
+pub fn execute_test_with_options(
+    test_artifact_path: &Path,
+    test_name: &str,
+    options: ExecuteTestOptions,
+) -> Result<TestResult> {
+    execute_artifact_test(
+        &TestArtifact::Path(test_artifact_path.to_path_buf()),
+        test_name,
+        options,
+    )
+}
+
+/// Execute a Solidity test of an artifact that may already be in memory, see
+/// [`execute_test_with_options`].
+pub fn execute_artifact_test(
+    test_artifact: &TestArtifact,
+    test_name: &str,
+    options: ExecuteTestOptions,
+) -> Result<TestResult> {
+    let ExecuteTestOptions {
+        perf_report_config,
+        scheduling,
+        sampling,
+        tsc_timing,
+        phase_timings,
+        memory_report,
+        cancellation,
+    } = options;
+
+    if let Some(cancellation) = &cancellation {
+        cancellation.check(ErrorPhase::Load)?;
+    }
+
+    // Applied before any setup so that the artifact is loaded on the CPU that executes the test.
+    let _scheduling_guard = scheduling
+        .as_ref()
+        .map(SchedulingGuard::apply)
+        .transpose()
+        .map_err(TestError::SchedulingFailed)?;
+
     let caller = address!("0100000000000000000000000000000000000000");
-    let deployed_code = load_test_contract_deployed_code(test_artifact_path)?;
     let contract_address = address!("4200000000000000000000000000000000000000");
 
+    let load_start = Instant::now();
+    let artifact = load_test_artifact(test_artifact)?;
+    let decode_start = Instant::now();
+    let deployed_code = decode_deployed_code(&artifact)?;
+    let analyse_start = Instant::now();
+    let bytecode = analyse_bytecode(deployed_code)?;
+    let db_start = Instant::now();
+    let db = create_db(contract_address, bytecode);
+    let build_start = Instant::now();
+
+    if artifact
+        .method_identifiers
+        .as_ref()
+        .is_some_and(|methods| !methods.contains_key(test_name))
+    {
+        return Err(TestError::UnknownTest {
+            test_name: test_name.to_string(),
+        }
+        .into());
+    }
     let selector = compute_selector(test_name);
 
-    let db = create_db(contract_address, deployed_code)?;
-
     // Create Context and build EVM
     let ctx: TestContext = Context::mainnet().with_db(db);
-    let mut evm = ctx.build_mainnet();
+    let mut evm = match cancellation.clone() {
+        Some(cancellation) => TestEvm::Cancellable(
+            ctx.build_mainnet_with_inspector(CancellationInspector::new(cancellation)),
+        ),
+        None => TestEvm::Plain(ctx.build_mainnet()),
+    };
 
     let test_tx = build_tx(contract_address, selector, caller)?;
+    let build_end = Instant::now();
+
+    let cpu_before = scheduling::current_cpu();
+
+    // Calibrated before the perf counters are enabled, since the first calibration busy-waits.
+    let tsc_frequency_hz = tsc_timing
+        .then(tsc::frequency_hz)
+        .transpose()
+        .map_err(instrumentation_failed(ErrorPhase::Setup))?;
+
+    // Set up before the perf counters are enabled, so that they don't count its page faults.
+    let mut sampler = sampling
+        .map(Sampler::new)
+        .transpose()
+        .map_err(instrumentation_failed(ErrorPhase::Setup))?;
 
     let mut perf_event_collector: Option<PerfEventCollector> = perf_report_config
         .map(|report_config| {
@@ -126,63 +286,235 @@ pub fn execute_test(
             pec.enable()?;
             Ok::<_, eyre::Error>(pec)
         })
-        .transpose()?;
+        .transpose()
+        .map_err(|source| TestError::PerfEventsUnavailable {
+            phase: ErrorPhase::Setup,
+            source,
+        })?;
+
+    let memory_tracker = memory_report
+        .then(MemoryTracker::start)
+        .transpose()
+        .map_err(instrumentation_failed(ErrorPhase::Setup))?;
+
+    // Only samples the REVM transaction, not the setup and reports of the other instrumentation.
+    if let Some(sampler) = sampler.as_mut() {
+        sampler
+            .enable()
+            .map_err(instrumentation_failed(ErrorPhase::Setup))?;
+    }
 
     let start = Instant::now();
     // Prefetch REVM transact code (which is heavily inlined) with max locality.
+    #[cfg(feature = "prefetch")]
     prefetch_read_instruction::<_, 3>(execute_test_transact as *const u8);
+    let tsc_start = tsc::start();
     let test_result = execute_test_transact(&mut evm, test_tx)?;
+    let tsc_end = tsc::end();
     let elapsed = start.elapsed();
 
+    if let Some(sampler) = sampler.as_mut() {
+        sampler
+            .disable()
+            .map_err(instrumentation_failed(ErrorPhase::Transact))?;
+    }
+
+    let cpu_after = scheduling::current_cpu();
+
+    // Read first, so that the counters don't include the work of the other reports.
     let perf_report = perf_event_collector
         .as_mut()
         .map(PerfEventCollector::report)
-        .transpose()?;
+        .transpose()
+        .map_err(|source| TestError::PerfEventsUnavailable {
+            phase: ErrorPhase::Transact,
+            source,
+        })?;
+
+    let memory = memory_tracker
+        .as_ref()
+        .map(MemoryTracker::report)
+        .transpose()
+        .map_err(instrumentation_failed(ErrorPhase::Transact))?;
+
+    let profile = sampler
+        .as_mut()
+        .map(Sampler::report)
+        .transpose()
+        .map_err(instrumentation_failed(ErrorPhase::Transact))?;
 
-    if !test_result.result.is_success() {
-        eyre::bail!("Test function reverted");
+    if let Some(cancellation) = &cancellation {
+        cancellation.check(ErrorPhase::Transact)?;
     }
 
+    let result_handling_start = Instant::now();
+    let failure = match test_result.result {
+        ExecutionResult::Success { .. } => None,
+        ExecutionResult::Revert { gas_used, output } => Some(TestError::TestReverted {
+            reason: session::decode_revert_reason(&output),
+            output,
+            gas_used,
+        }),
+        ExecutionResult::Halt { reason, gas_used } => Some(TestError::TestHalted {
+            reason: format!("{reason:?}"),
+            gas_used,
+        }),
+    };
+    drop(test_result.state);
+    drop(evm);
+    let result_handling_end = Instant::now();
+
+    if let Some(failure) = failure {
+        return Err(failure.into());
+    }
+
+    let phase_timings = phase_timings.then(|| PhaseTimings {
+        load_ns: (decode_start - load_start).as_nanos() as f64,
+        decode_ns: (analyse_start - decode_start).as_nanos() as f64,
+        analyse_ns: (db_start - analyse_start).as_nanos() as f64,
+        db_ns: (build_start - db_start).as_nanos() as f64,
+        build_ns: (build_end - build_start).as_nanos() as f64,
+        transact_ns: elapsed.as_nanos() as f64,
+        result_handling_ns: (result_handling_end - result_handling_start).as_nanos() as f64,
+    });
+
     Ok(TestResult {
         // Duration is expected to be <1m nanos so this is safe
         duration_ns: elapsed.as_nanos() as f64,
         perf_report,
+        thread_kind: None,
+        thread_name: std::thread::current().name().map(str::to_string),
+        cpu_before,
+        cpu_after,
+        profile,
+        tsc_timing: tsc_frequency_hz
+            .map(|frequency_hz| tsc::timing(tsc_start, tsc_end, frequency_hz)),
+        phase_timings,
+        memory,
+        allocator: Allocator::ACTIVE,
+        environment: environment().clone(),
     })
 }
 
+/// EVM of a test. Only cancellable tests are inspected, since the inspector hooks slow down the
+/// interpreter loop.
+enum TestEvm {
+    Plain(MainnetEvm<TestContext>),
+    Cancellable(MainnetEvm<TestContext, CancellationInspector>),
+}
+
 #[inline(never)]
-fn execute_test_transact(evm: &mut MainnetEvm<TestContext>, test_tx: TxEnv) -> Result<ExecResultAndState<ExecutionResult>> {
-    Ok(evm.transact(test_tx)?)
+fn execute_test_transact(
+    evm: &mut TestEvm,
+    test_tx: TxEnv,
+) -> Result<ExecResultAndState<ExecutionResult>> {
+    let result = match evm {
+        TestEvm::Plain(evm) => evm.transact(test_tx),
+        TestEvm::Cancellable(evm) => evm.inspect_tx(test_tx),
+    };
+    result.map_err(|err| {
+        TestError::EvmFailed {
+            message: err.to_string(),
+        }
+        .into()
+    })
+}
+
+fn instrumentation_failed(phase: ErrorPhase) -> impl FnOnce(eyre::Report) -> TestError {
+    move |source| TestError::InstrumentationFailed { phase, source }
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct Artifact {
+    /// Creation code, only needed to deploy the contract with its constructor.
+    #[serde(default)]
+    bytecode: Option<ArtifactCode>,
     deployed_bytecode: ArtifactCode,
+    /// Selectors by function signature, used to tell an unknown test from one that reverts.
+    #[serde(default)]
+    method_identifiers: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug, Deserialize)]
-struct ArtifactCode {
-    object: String,
+#[serde(untagged)]
+enum ArtifactCode {
+    Hex {
+        object: String,
+    },
+    /// Bytecode that was passed in already decoded.
+    #[serde(skip)]
+    Raw(Bytes),
+}
+
+fn load_test_artifact(test_artifact: &TestArtifact) -> Result<Artifact, TestError> {
+    match test_artifact {
+        TestArtifact::Path(path) => {
+            let artifact_file = match fs::File::open(path) {
+                Ok(artifact_file) => artifact_file,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    return Err(TestError::ArtifactNotFound { path: path.clone() })
+                }
+                Err(source) => {
+                    return Err(TestError::ArtifactUnreadable {
+                        path: path.clone(),
+                        source,
+                    })
+                }
+            };
+            serde_json::from_reader(artifact_file).map_err(|source| TestError::InvalidArtifact {
+                path: Some(path.clone()),
+                source,
+            })
+        }
+        TestArtifact::Json(json) => Artifact::deserialize(json)
+            .map_err(|source| TestError::InvalidArtifact { path: None, source }),
+        TestArtifact::Bytecode(bytecode) => Ok(Artifact {
+            bytecode: None,
+            deployed_bytecode: ArtifactCode::Raw(bytecode.clone()),
+            method_identifiers: None,
+        }),
+    }
+}
+
+fn decode_deployed_code(artifact: &Artifact) -> Result<Vec<u8>, TestError> {
+    decode_code(&artifact.deployed_bytecode)
 }
 
-fn load_test_contract_deployed_code(test_artifact_path: &Path) -> Result<Vec<u8>> {
-    let artifact_file = fs::File::open(test_artifact_path)?;
-    let artifact: Artifact = serde_json::from_reader(artifact_file)?;
-    let hex_str = artifact
-        .deployed_bytecode
-        .object
-        .strip_prefix("0x")
-        .unwrap_or(&artifact.deployed_bytecode.object);
-    let bytecode_bytes = hex::decode(hex_str)?;
-    Ok(bytecode_bytes)
+fn decode_creation_code(artifact: &Artifact) -> Result<Vec<u8>, TestError> {
+    let creation_code = artifact
+        .bytecode
+        .as_ref()
+        .ok_or_else(|| TestError::InvalidBytecode {
+            message: "Artifact has no creation bytecode".to_string(),
+        })?;
+    decode_code(creation_code)
 }
 
-fn create_db(contract_address: Address, contract_deployed_code: Vec<u8>) -> Result<InMemoryDB> {
+fn decode_code(code: &ArtifactCode) -> Result<Vec<u8>, TestError> {
+    match code {
+        ArtifactCode::Hex { object } => {
+            let hex_str = object.strip_prefix("0x").unwrap_or(object);
+            hex::decode(hex_str).map_err(|err| TestError::InvalidBytecode {
+                message: err.to_string(),
+            })
+        }
+        ArtifactCode::Raw(bytecode) => Ok(bytecode.to_vec()),
+    }
+}
+
+fn analyse_bytecode(contract_deployed_code: Vec<u8>) -> Result<Bytecode, TestError> {
+    Bytecode::new_raw_checked(contract_deployed_code.into()).map_err(|err| {
+        TestError::InvalidBytecode {
+            message: err.to_string(),
+        }
+    })
+}
+
+fn create_db(contract_address: Address, bytecode: Bytecode) -> InMemoryDB {
     let mut db = InMemoryDB::default();
 
     // Insert the contract bytecode into the database
-    let bytecode = Bytecode::new_raw_checked(contract_deployed_code.into())?;
     let account_info = AccountInfo {
         balance: U256::ZERO,
         nonce: 0,
@@ -191,7 +523,7 @@ fn create_db(contract_address: Address, contract_deployed_code: Vec<u8>) -> Resu
     };
     db.insert_account_info(contract_address, account_info);
 
-    Ok(db)
+    db
 }
 
 fn compute_selector(signature: &str) -> Bytes {
@@ -211,179 +543,124 @@ fn build_tx(contract_address: Address, selector: Bytes, caller: Address) -> Resu
     Ok(test_tx)
 }
 
-struct PerfEventCollector {
-    group: perf_event::Group,
-    cycles: Option<perf_event::Counter>,
-    instructions: Option<perf_event::Counter>,
-    last_level_cache_references: Option<perf_event::Counter>,
-    last_level_cache_misses: Option<perf_event::Counter>,
-    l1_data_cache_reads: Option<perf_event::Counter>,
-    l1_data_cache_misses: Option<perf_event::Counter>,
-    l1_instruction_cache_misses: Option<perf_event::Counter>,
-    branch_instructions: Option<perf_event::Counter>,
-    branch_misses: Option<perf_event::Counter>,
-    cpu_migrations: Option<perf_event::Counter>,
-}
-
-impl PerfEventCollector {
-    fn new(config: &PerfEventConfig) -> Result<Self> {
-        let mut group = perf_event::Group::new()?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
 
-        macro_rules! perf_event {
-            ($kind:expr) => {
-                perf_event::Builder::new()
-                    .group(&mut group)
-                    .kind($kind)
-                    .build()?
-            };
-        }
+    const TEST_ARTIFACT: &str = "../../contracts/Avg_Unit_Test.json";
+    const TEST_NAME: &str = "test_Avg_OneOperandEvenTheOtherOdd()";
 
-        macro_rules! optional_perf_event {
-            ($enabled:expr, $kind:expr) => {
-                if $enabled {
-                    Some(perf_event!($kind))
-                } else {
-                    None
-                }
-            };
-        }
+    #[test]
+    fn test_execute_test() -> Result<()> {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let artifact_path = manifest_dir.join(TEST_ARTIFACT);
 
-        let cycles = optional_perf_event!(config.cycles, perf_events::Hardware::CPU_CYCLES);
-        let instructions = optional_perf_event!(config.instructions, perf_events::Hardware::INSTRUCTIONS);
-        let last_level_cache_references = optional_perf_event!(config.last_level_cache_references, perf_events::Hardware::CACHE_REFERENCES);
-        let last_level_cache_misses = optional_perf_event!(config.last_level_cache_misses, perf_events::Hardware::CACHE_MISSES);
-        let l1_data_cache_reads = optional_perf_event!(config.l1_data_cache_reads, perf_events::Cache {
-            which: perf_events::WhichCache::L1D,
-            operation: perf_events::CacheOp::READ,
-            result: perf_events::CacheResult::ACCESS,
-        });
-        let l1_data_cache_misses = optional_perf_event!(config.l1_data_cache_misses, perf_events::Cache {
-            which: perf_events::WhichCache::L1D,
-            operation: perf_events::CacheOp::READ,
-            result: perf_events::CacheResult::MISS,
-        });
-        let l1_instruction_cache_misses = optional_perf_event!(config.l1_instruction_cache_misses, perf_events::Cache {
-            which: perf_events::WhichCache::L1I,
-            operation: perf_events::CacheOp::READ,
-            result: perf_events::CacheResult::MISS,
-        });
-        let branch_instructions = optional_perf_event!(config.branch_instructions, perf_events::Hardware::BRANCH_INSTRUCTIONS);
-        let branch_misses = optional_perf_event!(config.branch_misses, perf_events::Hardware::BRANCH_MISSES);
-        let cpu_migrations = optional_perf_event!(config.cpu_migrations, perf_events::Software::CPU_MIGRATIONS);
-
-        Ok(Self {
-            group,
-            cycles,
-            instructions,
-            last_level_cache_references,
-            last_level_cache_misses,
-            l1_data_cache_reads,
-            l1_data_cache_misses,
-            l1_instruction_cache_misses,
-            branch_instructions,
-            branch_misses,
-            cpu_migrations,
-        })
-    }
+        let test_result = execute_test(artifact_path.as_path(), TEST_NAME, None)?;
 
-    fn enable(&mut self) -> Result<()> {
-        self.group.enable()?;
+        assert!(test_result.duration_ns > 0.0);
+        assert!(test_result.perf_report.is_none());
+        assert!(test_result.phase_timings.is_none());
         Ok(())
     }
 
-    fn report(&mut self) -> Result<PerfReport> {
-        self.group.disable()?;
-        let counts = self.group.read()?;
-
-        macro_rules! count_to_f64 {
-            ($counter:expr) => {
-                $counter.as_ref().map(|c| {
-                    f64::from_u64(counts[c])
-                        .ok_or_else(|| eyre!("Failed to convert u64 to f64"))
-                }).transpose()?
-            };
-        }
+    #[test]
+    fn test_phase_timings() -> Result<()> {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let artifact_path = manifest_dir.join(TEST_ARTIFACT);
 
-        let instructions = count_to_f64!(&self.instructions);
-        let cycles = count_to_f64!(&self.cycles);
-        let instructions_per_cycle = match (instructions, cycles) {
-            (Some(instr), Some(cyc)) if cyc != 0.0 => Some(instr / cyc),
-            _ => None,
+        let options = ExecuteTestOptions {
+            phase_timings: true,
+            ..ExecuteTestOptions::default()
         };
+        let test_result = execute_test_with_options(artifact_path.as_path(), TEST_NAME, options)?;
+
+        let phases = test_result
+            .phase_timings
+            .expect("phase timings were requested");
+        assert_eq!(phases.transact_ns, test_result.duration_ns);
+        assert!(phases.load_ns > 0.0);
+        assert!(phases.decode_ns > 0.0);
+        assert!(phases.analyse_ns > 0.0);
+        assert!(phases.build_ns > 0.0);
+        Ok(())
+    }
 
-        let last_level_cache_misses = count_to_f64!(&self.last_level_cache_misses);
-        let last_level_cache_references = count_to_f64!(&self.last_level_cache_references);
-        let last_level_cache_hit_rate = match (last_level_cache_misses, last_level_cache_references) {
-            (Some(misses), Some(refs)) if refs != 0.0 => Some(1.0 - (misses / refs)),
-            _ => None,
-        };
+    #[test]
+    fn test_cancellation() -> Result<()> {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let artifact_path = manifest_dir.join(TEST_ARTIFACT);
 
-        let l1_data_cache_misses = count_to_f64!(&self.l1_data_cache_misses);
-        let l1_data_cache_reads = count_to_f64!(&self.l1_data_cache_reads);
-        let l1_data_cache_hit_rate = match (l1_data_cache_misses, l1_data_cache_reads) {
-            (Some(misses), Some(reads)) if reads != 0.0 => Some(1.0 - (misses / reads)),
-            _ => None,
+        let cancellation = CancellationToken::new();
+        let options = ExecuteTestOptions {
+            cancellation: Some(cancellation.clone()),
+            ..ExecuteTestOptions::default()
         };
+        execute_test_with_options(artifact_path.as_path(), TEST_NAME, options.clone())?;
+
+        cancellation.cancel();
+        let err =
+            execute_test_with_options(artifact_path.as_path(), TEST_NAME, options).unwrap_err();
+        let err = err.downcast_ref::<TestError>().expect("typed error");
+        assert_eq!(
+            (err.code(), err.phase()),
+            (ErrorCode::Aborted, ErrorPhase::Load)
+        );
+
+        // A running transaction halts at the next instruction, even if that is `STOP`.
+        let stop = Bytecode::new_raw(Bytes::from_static(&[0x00]));
+        let ctx: TestContext = Context::mainnet().with_db(create_db(Address::ZERO, stop));
+        let mut evm = TestEvm::Cancellable(
+            ctx.build_mainnet_with_inspector(CancellationInspector::new(cancellation)),
+        );
+        let test_tx = build_tx(Address::ZERO, Bytes::new(), Address::with_last_byte(1))?;
+        assert!(!execute_test_transact(&mut evm, test_tx)?
+            .result
+            .is_success());
+        Ok(())
+    }
 
-        let l1_instruction_cache_misses = count_to_f64!(&self.l1_instruction_cache_misses);
-
-        let branch_misses = count_to_f64!(&self.branch_misses);
-        let branch_instructions = count_to_f64!(&self.branch_instructions);
-        let branch_miss_ratio = match (branch_misses, branch_instructions) {
-            (Some(misses), Some(instrs)) if instrs != 0.0 => Some(misses / instrs),
-            _ => None,
+    #[test]
+    fn test_error_codes() {
+        let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let artifact_path = manifest_dir.join(TEST_ARTIFACT);
+        let code = |path: &Path, test_name: &str| {
+            let err = execute_test(path, test_name, None).unwrap_err();
+            err.downcast_ref::<TestError>().map(TestError::code)
         };
 
-        let cpu_migrations = count_to_f64!(&self.cpu_migrations);
-
-        Ok(PerfReport {
-            instructions,
-            instructions_per_cycle,
-            last_level_cache_hit_rate,
-            l1_data_cache_hit_rate,
-            l1_instruction_cache_misses,
-            branch_miss_ratio,
-            cpu_migrations,
-        })
+        assert_eq!(
+            code(&manifest_dir.join("missing.json"), TEST_NAME),
+            Some(ErrorCode::ArtifactNotFound)
+        );
+        assert_eq!(
+            code(&manifest_dir.join("Cargo.toml"), TEST_NAME),
+            Some(ErrorCode::InvalidArtifact)
+        );
+        assert_eq!(
+            code(&artifact_path, "test_missing()"),
+            Some(ErrorCode::UnknownTest)
+        );
     }
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct PerfReport {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub instructions: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub instructions_per_cycle: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub last_level_cache_hit_rate: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub l1_data_cache_hit_rate: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub l1_instruction_cache_misses: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub branch_miss_ratio: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub cpu_migrations: Option<f64>,
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::path::PathBuf;
-
-    const TEST_ARTIFACT: &str = "../../contracts/Avg_Unit_Test.json";
-    const TEST_NAME: &str = "test_Avg_OneOperandEvenTheOtherOdd()";
 
     #[test]
-    fn test_execute_test() -> Result<()> {
+    fn test_execute_artifact_test() -> Result<()> {
         let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        let artifact_path = manifest_dir.join(TEST_ARTIFACT);
+        let json = fs::read_to_string(manifest_dir.join(TEST_ARTIFACT))?;
+        let artifact = TestArtifact::from_json_str(&json)?;
+        execute_artifact_test(&artifact, TEST_NAME, ExecuteTestOptions::default())?;
 
-        let test_result = execute_test(artifact_path.as_path(), TEST_NAME, None)?;
-
-        assert!(test_result.duration_ns > 0.0);
-        assert!(test_result.perf_report.is_none());
+        let TestArtifact::Json(json) = artifact else {
+            unreachable!()
+        };
+        let deployed_code = json["deployedBytecode"]["object"]
+            .as_str()
+            .expect("artifact has deployed bytecode");
+        let bytecode = TestArtifact::Bytecode(deployed_code.parse()?);
+        execute_artifact_test(&bytecode, TEST_NAME, ExecuteTestOptions::default())?;
+
+        let err = TestArtifact::from_json_str("{").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidArtifact);
         Ok(())
     }
 }
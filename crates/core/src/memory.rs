@@ -0,0 +1,250 @@
+use eyre::Result;
+use serde::Serialize;
+#[cfg(target_os = "linux")]
+use std::{io, mem};
+
+/// Memory accounting of the REVM transaction.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryReport {
+    /// Heap allocations of the executing thread, only counted when built with the
+    /// `counting-allocator` feature.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allocations: Option<AllocationStats>,
+    /// Growth of the process' peak resident set size in KiB.
+    pub max_rss_delta_kb: u64,
+    /// Page faults of the executing thread that were served without I/O.
+    pub minor_page_faults: u64,
+    /// Page faults of the executing thread that required I/O.
+    pub major_page_faults: u64,
+}
+
+/// Heap allocations made by one thread while it was measured.
+///
+/// A `realloc` counts as one deallocation of the old size and one allocation of the new size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AllocationStats {
+    pub allocations: u64,
+    pub deallocations: u64,
+    pub bytes_allocated: u64,
+    /// Highest number of bytes allocated by the thread and not yet freed, relative to the start
+    /// of the measurement.
+    pub peak_bytes: u64,
+}
+
+/// Snapshot of the executing thread's memory counters taken right before the transaction.
+#[cfg(target_os = "linux")]
+pub(crate) struct MemoryTracker {
+    rusage: libc::rusage,
+    #[cfg(feature = "counting-allocator")]
+    allocations: counting::Snapshot,
+}
+
+#[cfg(target_os = "linux")]
+impl MemoryTracker {
+    pub(crate) fn start() -> Result<Self> {
+        Ok(Self {
+            rusage: thread_rusage()?,
+            #[cfg(feature = "counting-allocator")]
+            allocations: counting::Snapshot::start(),
+        })
+    }
+
+    pub(crate) fn report(&self) -> Result<MemoryReport> {
+        #[cfg(feature = "counting-allocator")]
+        let allocations = Some(self.allocations.stats());
+        #[cfg(not(feature = "counting-allocator"))]
+        let allocations = None;
+
+        let rusage = thread_rusage()?;
+        Ok(MemoryReport {
+            allocations,
+            max_rss_delta_kb: (rusage.ru_maxrss - self.rusage.ru_maxrss).max(0) as u64,
+            minor_page_faults: (rusage.ru_minflt - self.rusage.ru_minflt).max(0) as u64,
+            major_page_faults: (rusage.ru_majflt - self.rusage.ru_majflt).max(0) as u64,
+        })
+    }
+}
+
+/// Stand-in for targets without per-thread `getrusage`, which can't be constructed.
+#[cfg(not(target_os = "linux"))]
+pub(crate) struct MemoryTracker(std::convert::Infallible);
+
+#[cfg(not(target_os = "linux"))]
+impl MemoryTracker {
+    pub(crate) fn start() -> Result<Self> {
+        Err(eyre::eyre!("Memory accounting is only supported on Linux"))
+    }
+
+    pub(crate) fn report(&self) -> Result<MemoryReport> {
+        match self.0 {}
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn thread_rusage() -> io::Result<libc::rusage> {
+    // SAFETY: `rusage` is plain data for which all zeroes is a valid value and the pointer is
+    // valid for writes.
+    let mut usage: libc::rusage = unsafe { mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_THREAD, &mut usage) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(usage)
+}
+
+#[cfg(feature = "counting-allocator")]
+pub(crate) mod counting {
+    use super::AllocationStats;
+    use std::{
+        alloc::{GlobalAlloc, Layout},
+        cell::Cell,
+    };
+
+    /// Global allocator that counts the allocations of each thread before forwarding them to
+    /// `A`.
+    pub(crate) struct CountingAllocator<A> {
+        inner: A,
+    }
+
+    impl<A> CountingAllocator<A> {
+        pub(crate) const fn new(inner: A) -> Self {
+            Self { inner }
+        }
+    }
+
+    // SAFETY: All calls are forwarded to `inner` unchanged.
+    unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let ptr = unsafe { self.inner.alloc(layout) };
+            if !ptr.is_null() {
+                record_alloc(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            let ptr = unsafe { self.inner.alloc_zeroed(layout) };
+            if !ptr.is_null() {
+                record_alloc(layout.size());
+            }
+            ptr
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { self.inner.dealloc(ptr, layout) };
+            record_dealloc(layout.size());
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let new_ptr = unsafe { self.inner.realloc(ptr, layout, new_size) };
+            if !new_ptr.is_null() {
+                record_dealloc(layout.size());
+                record_alloc(new_size);
+            }
+            new_ptr
+        }
+    }
+
+    /// Counters of the current thread. Plain `Cell`s without a destructor so that they can be
+    /// used from the allocator without allocating and are never torn down.
+    struct ThreadCounters {
+        allocations: Cell<u64>,
+        deallocations: Cell<u64>,
+        bytes_allocated: Cell<u64>,
+        live_bytes: Cell<i64>,
+        peak_live_bytes: Cell<i64>,
+    }
+
+    thread_local! {
+        static COUNTERS: ThreadCounters = const {
+            ThreadCounters {
+                allocations: Cell::new(0),
+                deallocations: Cell::new(0),
+                bytes_allocated: Cell::new(0),
+                live_bytes: Cell::new(0),
+                peak_live_bytes: Cell::new(0),
+            }
+        };
+    }
+
+    fn record_alloc(size: usize) {
+        let _ = COUNTERS.try_with(|counters| {
+            counters.allocations.set(counters.allocations.get() + 1);
+            counters
+                .bytes_allocated
+                .set(counters.bytes_allocated.get() + size as u64);
+            let live_bytes = counters.live_bytes.get() + size as i64;
+            counters.live_bytes.set(live_bytes);
+            if live_bytes > counters.peak_live_bytes.get() {
+                counters.peak_live_bytes.set(live_bytes);
+            }
+        });
+    }
+
+    fn record_dealloc(size: usize) {
+        let _ = COUNTERS.try_with(|counters| {
+            counters.deallocations.set(counters.deallocations.get() + 1);
+            // Memory allocated by another thread makes this go negative, which is fine since
+            // only differences are reported.
+            counters
+                .live_bytes
+                .set(counters.live_bytes.get() - size as i64);
+        });
+    }
+
+    pub(crate) struct Snapshot {
+        allocations: u64,
+        deallocations: u64,
+        bytes_allocated: u64,
+        live_bytes: i64,
+    }
+
+    impl Snapshot {
+        /// Takes a snapshot and resets the thread's peak so that it's relative to this point.
+        pub(crate) fn start() -> Self {
+            COUNTERS.with(|counters| {
+                counters.peak_live_bytes.set(counters.live_bytes.get());
+                Self {
+                    allocations: counters.allocations.get(),
+                    deallocations: counters.deallocations.get(),
+                    bytes_allocated: counters.bytes_allocated.get(),
+                    live_bytes: counters.live_bytes.get(),
+                }
+            })
+        }
+
+        pub(crate) fn stats(&self) -> AllocationStats {
+            COUNTERS.with(|counters| AllocationStats {
+                allocations: counters.allocations.get() - self.allocations,
+                deallocations: counters.deallocations.get() - self.deallocations,
+                bytes_allocated: counters.bytes_allocated.get() - self.bytes_allocated,
+                peak_bytes: (counters.peak_live_bytes.get() - self.live_bytes).max(0) as u64,
+            })
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_tracker_reports_allocations() -> Result<()> {
+        let tracker = MemoryTracker::start()?;
+        let buffer = std::hint::black_box(vec![1u8; 1 << 20]);
+        drop(buffer);
+        let report = tracker.report()?;
+
+        if cfg!(feature = "counting-allocator") {
+            let allocations = report.allocations.expect("counted by the global allocator");
+            assert!(allocations.allocations >= 1);
+            assert!(allocations.deallocations >= 1);
+            assert!(allocations.bytes_allocated >= 1 << 20);
+            assert!(allocations.peak_bytes >= 1 << 20);
+        } else {
+            assert!(report.allocations.is_none());
+        }
+        Ok(())
+    }
+}
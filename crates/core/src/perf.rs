@@ -0,0 +1,1596 @@
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, str::FromStr};
+#[cfg(feature = "perf")]
+use {
+    crate::memory::thread_rusage,
+    eyre::bail,
+    perf_event::events as perf_events,
+    std::{collections::BTreeSet, io},
+};
+
+/// `perf_event_attr.type` for CPU-specific raw events.
+pub const PERF_TYPE_RAW: u32 = 4;
+
+/// `perf_event_attr.type` for generic hardware events.
+pub(crate) const PERF_TYPE_HARDWARE: u32 = 0;
+
+/// `perf_event_attr.type` for software events, which don't occupy PMU counters.
+pub(crate) const PERF_TYPE_SOFTWARE: u32 = 1;
+
+/// `PERF_COUNT_SW_CONTEXT_SWITCHES`, `PERF_COUNT_SW_CPU_MIGRATIONS` and
+/// `PERF_COUNT_SW_CGROUP_SWITCHES`, the software events that are counted by the scheduler.
+#[cfg(feature = "perf")]
+const SCHED_SOFTWARE_EVENTS: [u64; 3] = [3, 4, 11];
+
+/// `PERF_COUNT_SW_CPU_CLOCK` and `PERF_COUNT_SW_TASK_CLOCK`, the software events that count time.
+#[cfg(feature = "perf")]
+const CLOCK_SOFTWARE_EVENTS: [u64; 2] = [0, 1];
+
+/// Frequently used microarchitecture-specific events as `(name, config, config1)` raw encodings for
+/// Intel Skylake and later cores. Other CPUs need their encodings passed as [`RawEventConfig`].
+const NAMED_EVENTS: &[(&str, u64, u64)] = &[
+    ("frontend_retired.dsb_miss", 0x01c6, 0x11),
+    ("frontend_retired.l1i_miss", 0x01c6, 0x12),
+    ("frontend_retired.l2_miss", 0x01c6, 0x13),
+    ("frontend_retired.itlb_miss", 0x01c6, 0x14),
+    ("idq.dsb_uops", 0x0879, 0),
+    ("idq.mite_uops", 0x0479, 0),
+    ("idq.ms_uops", 0x3079, 0),
+    ("dsb2mite_switches.penalty_cycles", 0x02ab, 0),
+    ("icache_64b.iftag_miss", 0x0283, 0),
+    ("uops_issued.any", 0x010e, 0),
+    ("uops_retired.retire_slots", 0x02c2, 0),
+    ("cycle_activity.stalls_total", 0x0400_04a3, 0),
+];
+
+/// A PMU event given by its raw `perf_event_attr` encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawEventConfig {
+    /// Name under which the count is reported in [`PerfReport::counters`].
+    pub name: String,
+    /// `perf_event_attr.type`: [`PERF_TYPE_RAW`] or a dynamic PMU type from
+    /// `/sys/bus/event_source/devices/*/type`.
+    pub event_type: u32,
+    /// `perf_event_attr.config`
+    pub config: u64,
+    /// `perf_event_attr.config1`, which some events use for extra settings (e.g. the frontend
+    /// event MSR of `frontend_retired.*`).
+    pub config1: u64,
+}
+
+impl RawEventConfig {
+    /// Look up an event from the named event table.
+    pub fn named(name: &str) -> Option<Self> {
+        NAMED_EVENTS
+            .iter()
+            .find(|(event_name, _, _)| *event_name == name)
+            .map(|&(name, config, config1)| Self {
+                name: name.to_string(),
+                event_type: PERF_TYPE_RAW,
+                config,
+                config1,
+            })
+    }
+
+    /// Names of the events in the named event table.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        NAMED_EVENTS.iter().map(|(name, _, _)| *name)
+    }
+}
+
+impl FromStr for RawEventConfig {
+    type Err = String;
+
+    /// Parses `name=type:config[:config1]`, where the codes are hexadecimal with an optional `0x`
+    /// prefix, e.g. `l1i_miss=4:0x1c6:0x12`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expected = "expected `name=type:config[:config1]` with hexadecimal codes";
+        let (name, codes) = s
+            .split_once('=')
+            .ok_or_else(|| format!("{expected}, got `{s}`"))?;
+        let codes = codes
+            .split(':')
+            .map(parse_hex)
+            .collect::<Result<Vec<_>, _>>()?;
+        let (event_type, config, config1) = match codes[..] {
+            [event_type, config] => (event_type, config, 0),
+            [event_type, config, config1] => (event_type, config, config1),
+            _ => return Err(format!("{expected}, got `{s}`")),
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            event_type: u32::try_from(event_type)
+                .map_err(|_| format!("event type {event_type:#x} does not fit in 32 bits"))?,
+            config,
+            config1,
+        })
+    }
+}
+
+/// Parse a hexadecimal number with an optional `0x` prefix.
+pub fn parse_hex(s: &str) -> Result<u64, String> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    u64::from_str_radix(digits, 16)
+        .map_err(|err| format!("invalid hexadecimal number `{s}`: {err}"))
+}
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(not(feature = "perf"), allow(dead_code))]
+pub(crate) struct PerfEventConfig {
+    cycles: bool,
+    instructions: bool,
+    last_level_cache_references: bool,
+    last_level_cache_misses: bool,
+    l1_data_cache_reads: bool,
+    l1_data_cache_misses: bool,
+    l1_instruction_cache_misses: bool,
+    branch_instructions: bool,
+    branch_misses: bool,
+    cpu_migrations: bool,
+    instruction_tlb_misses: bool,
+    data_tlb_reads: bool,
+    data_tlb_misses: bool,
+    page_faults_minor: bool,
+    page_faults_major: bool,
+    context_switches: bool,
+    task_clock: bool,
+    stalled_cycles_frontend: bool,
+    stalled_cycles_backend: bool,
+    ref_cycles: bool,
+    raw_events: Vec<RawEventConfig>,
+    named_events: Vec<String>,
+    max_hardware_events_per_group: Option<usize>,
+    multiplexing: MultiplexingPolicy,
+    counting_mode: CountingMode,
+    include_hypervisor: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PerfReportConfig {
+    pub instructions: bool,
+    pub instructions_per_cycle: bool,
+    pub last_level_cache_hit_rate: bool,
+    pub l1_data_cache_hit_rate: bool,
+    pub l1_instruction_cache_misses: bool,
+    pub branch_miss_ratio: bool,
+    pub cpu_migrations: bool,
+    /// Instruction TLB misses and misses per thousand instructions.
+    pub instruction_tlb_misses: bool,
+    /// Data TLB read miss ratio.
+    pub data_tlb_miss_ratio: bool,
+    /// Minor and major page faults.
+    pub page_faults: bool,
+    /// Context switches, split into voluntary and involuntary ones.
+    pub context_switches: bool,
+    /// Task clock and the share of wall-clock time the task was running.
+    pub task_clock: bool,
+    /// Share of cycles stalled in the frontend and backend.
+    pub stalled_cycles: bool,
+    /// Reference cycles and cycles per reference cycle, which reveals frequency scaling.
+    pub ref_cycles: bool,
+    /// Raw PMU events reported in [`PerfReport::counters`].
+    pub raw_events: Vec<RawEventConfig>,
+    /// Events from the named event table (see [`RawEventConfig::names`]) reported in
+    /// [`PerfReport::counters`].
+    pub named_events: Vec<String>,
+    /// Split counters into groups of at most this many hardware events so that every group fits on
+    /// the PMU. Defaults to [`DEFAULT_MAX_HARDWARE_EVENTS_PER_GROUP`].
+    pub max_hardware_events_per_group: Option<usize>,
+    /// How to handle groups that were multiplexed with each other.
+    pub multiplexing: MultiplexingPolicy,
+    /// Whether to count events in user space, kernel space or both.
+    pub counting_mode: CountingMode,
+    /// Also count events while the hypervisor runs on behalf of the thread, excluded by default.
+    pub include_hypervisor: bool,
+}
+
+impl PerfReportConfig {
+    /// Whether no metric is requested.
+    pub fn is_empty(&self) -> bool {
+        let PerfReportConfig {
+            instructions,
+            instructions_per_cycle,
+            last_level_cache_hit_rate,
+            l1_data_cache_hit_rate,
+            l1_instruction_cache_misses,
+            branch_miss_ratio,
+            cpu_migrations,
+            instruction_tlb_misses,
+            data_tlb_miss_ratio,
+            page_faults,
+            context_switches,
+            task_clock,
+            stalled_cycles,
+            ref_cycles,
+            raw_events,
+            named_events,
+            max_hardware_events_per_group: _,
+            multiplexing: _,
+            counting_mode: _,
+            include_hypervisor: _,
+        } = self;
+
+        !(*instructions
+            || *instructions_per_cycle
+            || *last_level_cache_hit_rate
+            || *l1_data_cache_hit_rate
+            || *l1_instruction_cache_misses
+            || *branch_miss_ratio
+            || *cpu_migrations
+            || *instruction_tlb_misses
+            || *data_tlb_miss_ratio
+            || *page_faults
+            || *context_switches
+            || *task_clock
+            || *stalled_cycles
+            || *ref_cycles
+            || !raw_events.is_empty()
+            || !named_events.is_empty())
+    }
+}
+
+impl From<PerfReportConfig> for PerfEventConfig {
+    fn from(value: PerfReportConfig) -> Self {
+        let PerfReportConfig {
+            instructions,
+            instructions_per_cycle,
+            last_level_cache_hit_rate,
+            l1_data_cache_hit_rate,
+            l1_instruction_cache_misses,
+            branch_miss_ratio,
+            cpu_migrations,
+            instruction_tlb_misses,
+            data_tlb_miss_ratio,
+            page_faults,
+            context_switches,
+            task_clock,
+            stalled_cycles,
+            ref_cycles,
+            raw_events,
+            named_events,
+            max_hardware_events_per_group,
+            multiplexing,
+            counting_mode,
+            include_hypervisor,
+        } = value;
+
+        let mut config = PerfEventConfig {
+            raw_events,
+            named_events,
+            max_hardware_events_per_group,
+            multiplexing,
+            counting_mode,
+            include_hypervisor,
+            ..PerfEventConfig::default()
+        };
+
+        if instructions {
+            config.instructions = true;
+        }
+
+        if instructions_per_cycle {
+            config.cycles = true;
+            config.instructions = true;
+        }
+
+        if last_level_cache_hit_rate {
+            config.last_level_cache_references = true;
+            config.last_level_cache_misses = true;
+        }
+
+        if l1_data_cache_hit_rate {
+            config.l1_data_cache_reads = true;
+            config.l1_data_cache_misses = true;
+        }
+
+        if l1_instruction_cache_misses {
+            config.l1_instruction_cache_misses = true;
+        }
+
+        if branch_miss_ratio {
+            config.branch_instructions = true;
+            config.branch_misses = true;
+        }
+
+        if cpu_migrations {
+            config.cpu_migrations = true;
+        }
+
+        if instruction_tlb_misses {
+            config.instruction_tlb_misses = true;
+            config.instructions = true;
+        }
+
+        if data_tlb_miss_ratio {
+            config.data_tlb_reads = true;
+            config.data_tlb_misses = true;
+        }
+
+        if page_faults {
+            config.page_faults_minor = true;
+            config.page_faults_major = true;
+        }
+
+        if context_switches {
+            config.context_switches = true;
+        }
+
+        if task_clock {
+            config.task_clock = true;
+        }
+
+        if stalled_cycles {
+            config.cycles = true;
+            config.stalled_cycles_frontend = true;
+            config.stalled_cycles_backend = true;
+        }
+
+        if ref_cycles {
+            config.cycles = true;
+            config.ref_cycles = true;
+        }
+
+        config
+    }
+}
+
+/// Counter groups are split so that they don't need more than this many hardware counters, unless
+/// configured otherwise. Intel cores have four general-purpose counters per hyper-thread (in addition
+/// to fixed ones), so this size always fits.
+pub const DEFAULT_MAX_HARDWARE_EVENTS_PER_GROUP: usize = 4;
+
+/// What to do with counts of a group that the kernel multiplexed with other groups, i.e. that was
+/// only counting for part of the time it was enabled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MultiplexingPolicy {
+    /// Extrapolate counts by `time_enabled / time_running`, like `perf stat` does.
+    #[default]
+    Scale,
+    /// Fail instead of reporting extrapolated counts.
+    Reject,
+}
+
+impl FromStr for MultiplexingPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scale" => Ok(Self::Scale),
+            "reject" => Ok(Self::Reject),
+            _ => Err(format!(
+                "unknown multiplexing policy `{s}`, expected `scale` or `reject`"
+            )),
+        }
+    }
+}
+
+/// Privilege levels at which events are counted.
+///
+/// The kernel honours the mode for PMU events and page faults only. Context switches, CPU
+/// migrations and cgroup switches happen in the scheduler, so they are counted in kernel mode
+/// instead of user mode. The task and CPU clocks count all time the thread runs in any mode. Such
+/// counters are listed in [`PerfReport::effective_counting_modes`]. `include_hypervisor` only
+/// applies to PMU events.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CountingMode {
+    /// Only count while the thread runs in user space, i.e. REVM's own work.
+    #[default]
+    User,
+    /// Only count kernel work on behalf of the thread, like page faults and scheduling. Requires
+    /// `perf_event_paranoid` of 1 or lower or `CAP_PERFMON`.
+    Kernel,
+    /// Count both user and kernel space.
+    UserAndKernel,
+}
+
+#[cfg(feature = "perf")]
+impl CountingMode {
+    /// Restricts the event to the counting mode and returns the mode it is actually counted in,
+    /// which differs for the software events that the kernel can't restrict.
+    fn configure(self, builder: &mut perf_event::Builder, include_hypervisor: bool) -> Self {
+        let attrs = builder.attrs();
+        let effective_mode = match (attrs.type_, attrs.config) {
+            // Excluding the kernel would always read them as zero.
+            (PERF_TYPE_SOFTWARE, config) if SCHED_SOFTWARE_EVENTS.contains(&config) => match self {
+                CountingMode::User => CountingMode::Kernel,
+                mode => mode,
+            },
+            // Counting clocks ignore the exclude bits.
+            (PERF_TYPE_SOFTWARE, config) if CLOCK_SOFTWARE_EVENTS.contains(&config) => {
+                CountingMode::UserAndKernel
+            }
+            _ => self,
+        };
+        builder
+            .exclude_user(effective_mode == CountingMode::Kernel)
+            .exclude_kernel(effective_mode == CountingMode::User)
+            .exclude_hv(!include_hypervisor);
+        effective_mode
+    }
+}
+
+impl FromStr for CountingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(Self::User),
+            "kernel" => Ok(Self::Kernel),
+            "user-and-kernel" => Ok(Self::UserAndKernel),
+            _ => Err(format!(
+                "unknown counting mode `{s}`, expected `user`, `kernel` or `user-and-kernel`"
+            )),
+        }
+    }
+}
+
+/// Built-in events by the name they are reported under in [`PerfReport::running_ratios`] and
+/// [`PerfReport::unavailable`]. Counters that derived metrics divide by each other are opened in
+/// the same group, see [`GroupAllocator::add_next_to`].
+#[cfg(feature = "perf")]
+const BUILTIN_EVENTS: &[(&str, perf_events::Event)] = &[
+    (
+        "cycles",
+        perf_events::Event::Hardware(perf_events::Hardware::CPU_CYCLES),
+    ),
+    (
+        "instructions",
+        perf_events::Event::Hardware(perf_events::Hardware::INSTRUCTIONS),
+    ),
+    (
+        "ref_cycles",
+        perf_events::Event::Hardware(perf_events::Hardware::REF_CPU_CYCLES),
+    ),
+    (
+        "stalled_cycles_frontend",
+        perf_events::Event::Hardware(perf_events::Hardware::STALLED_CYCLES_FRONTEND),
+    ),
+    (
+        "stalled_cycles_backend",
+        perf_events::Event::Hardware(perf_events::Hardware::STALLED_CYCLES_BACKEND),
+    ),
+    (
+        "last_level_cache_references",
+        perf_events::Event::Hardware(perf_events::Hardware::CACHE_REFERENCES),
+    ),
+    (
+        "last_level_cache_misses",
+        perf_events::Event::Hardware(perf_events::Hardware::CACHE_MISSES),
+    ),
+    (
+        "l1_data_cache_reads",
+        perf_events::Event::Cache(perf_events::Cache {
+            which: perf_events::WhichCache::L1D,
+            operation: perf_events::CacheOp::READ,
+            result: perf_events::CacheResult::ACCESS,
+        }),
+    ),
+    (
+        "l1_data_cache_misses",
+        perf_events::Event::Cache(perf_events::Cache {
+            which: perf_events::WhichCache::L1D,
+            operation: perf_events::CacheOp::READ,
+            result: perf_events::CacheResult::MISS,
+        }),
+    ),
+    (
+        "l1_instruction_cache_misses",
+        perf_events::Event::Cache(perf_events::Cache {
+            which: perf_events::WhichCache::L1I,
+            operation: perf_events::CacheOp::READ,
+            result: perf_events::CacheResult::MISS,
+        }),
+    ),
+    (
+        "branch_instructions",
+        perf_events::Event::Hardware(perf_events::Hardware::BRANCH_INSTRUCTIONS),
+    ),
+    (
+        "branch_misses",
+        perf_events::Event::Hardware(perf_events::Hardware::BRANCH_MISSES),
+    ),
+    (
+        "instruction_tlb_misses",
+        perf_events::Event::Cache(perf_events::Cache {
+            which: perf_events::WhichCache::ITLB,
+            operation: perf_events::CacheOp::READ,
+            result: perf_events::CacheResult::MISS,
+        }),
+    ),
+    (
+        "data_tlb_reads",
+        perf_events::Event::Cache(perf_events::Cache {
+            which: perf_events::WhichCache::DTLB,
+            operation: perf_events::CacheOp::READ,
+            result: perf_events::CacheResult::ACCESS,
+        }),
+    ),
+    (
+        "data_tlb_misses",
+        perf_events::Event::Cache(perf_events::Cache {
+            which: perf_events::WhichCache::DTLB,
+            operation: perf_events::CacheOp::READ,
+            result: perf_events::CacheResult::MISS,
+        }),
+    ),
+    (
+        "cpu_migrations",
+        perf_events::Event::Software(perf_events::Software::CPU_MIGRATIONS),
+    ),
+    (
+        "page_faults_minor",
+        perf_events::Event::Software(perf_events::Software::PAGE_FAULTS_MIN),
+    ),
+    (
+        "page_faults_major",
+        perf_events::Event::Software(perf_events::Software::PAGE_FAULTS_MAJ),
+    ),
+    (
+        "context_switches",
+        perf_events::Event::Software(perf_events::Software::CONTEXT_SWITCHES),
+    ),
+    (
+        "task_clock",
+        perf_events::Event::Software(perf_events::Software::TASK_CLOCK),
+    ),
+];
+
+#[cfg(feature = "perf")]
+struct CounterHandle {
+    name: String,
+    group: usize,
+    counter: perf_event::Counter,
+}
+
+/// Distributes counters over groups so that no group needs more hardware counters than the PMU has.
+/// A group that doesn't fit is never scheduled and would silently read as zero.
+///
+/// Counters that can't be opened are recorded in `unavailable` instead of failing, so that a report
+/// can still be produced on machines without (full) PMU access.
+#[cfg(feature = "perf")]
+struct GroupAllocator {
+    groups: Vec<perf_event::Group>,
+    hardware_events_in_last_group: usize,
+    max_hardware_events_per_group: usize,
+    counting_mode: CountingMode,
+    include_hypervisor: bool,
+    unavailable: BTreeMap<String, String>,
+    /// See [`PerfReport::effective_counting_modes`].
+    effective_counting_modes: BTreeMap<String, CountingMode>,
+    /// Copies of counters that were opened again next to a counter they are divided by or the
+    /// other way round, because their own group was full.
+    shared_inputs: Vec<CounterHandle>,
+}
+
+#[cfg(feature = "perf")]
+impl GroupAllocator {
+    fn new(
+        max_hardware_events_per_group: usize,
+        counting_mode: CountingMode,
+        include_hypervisor: bool,
+    ) -> Self {
+        Self {
+            groups: Vec::new(),
+            hardware_events_in_last_group: 0,
+            max_hardware_events_per_group: max_hardware_events_per_group.max(1),
+            counting_mode,
+            include_hypervisor,
+            unavailable: BTreeMap::new(),
+            effective_counting_modes: BTreeMap::new(),
+            shared_inputs: Vec::new(),
+        }
+    }
+
+    /// Opens a new group unless the last one has room for `count` more hardware events.
+    fn reserve_hardware_events(&mut self, count: usize) -> io::Result<()> {
+        if self.groups.is_empty()
+            || self.hardware_events_in_last_group + count > self.max_hardware_events_per_group
+        {
+            self.groups.push(perf_event::Group::new()?);
+            self.hardware_events_in_last_group = 0;
+        }
+        Ok(())
+    }
+
+    /// Adds the counter `name` to a group with `input`, the counter that a derived metric divides
+    /// it by or the other way round, so that both are counted over the same time windows when the
+    /// groups are multiplexed. If that group is full, `input` is opened again next to `name` in a
+    /// new group.
+    fn add_next_to(
+        &mut self,
+        name: &str,
+        input: Option<&CounterHandle>,
+        add: fn(&mut Self, &str) -> Result<Option<CounterHandle>>,
+    ) -> Result<Option<CounterHandle>> {
+        let Some(input) = input else {
+            return add(self, name);
+        };
+        // Opening `input` needed a group, so there is a last one.
+        let last_group = self.groups.len() - 1;
+        let fits_next_to_input = find_in_group(&self.shared_inputs, input, last_group).is_some()
+            && self.hardware_events_in_last_group < self.max_hardware_events_per_group;
+        // With a single hardware event per group the two can't share a group.
+        if fits_next_to_input || self.max_hardware_events_per_group < 2 {
+            return add(self, name);
+        }
+
+        self.reserve_hardware_events(2)?;
+        if let Some(copy) = add(self, &input.name)? {
+            self.shared_inputs.push(copy);
+        }
+        add(self, name)
+    }
+
+    fn next_group(&mut self, hardware: bool) -> io::Result<usize> {
+        let needs_new_group = self.groups.is_empty()
+            || (hardware
+                && self.hardware_events_in_last_group >= self.max_hardware_events_per_group);
+        if needs_new_group {
+            self.groups.push(perf_event::Group::new()?);
+            self.hardware_events_in_last_group = 0;
+        }
+
+        // Software events don't occupy PMU counters, so they can always join the first group.
+        if hardware {
+            Ok(self.groups.len() - 1)
+        } else {
+            Ok(0)
+        }
+    }
+
+    fn add(
+        &mut self,
+        name: &str,
+        hardware: bool,
+        configure: impl Fn(perf_event::Builder<'_>) -> perf_event::Builder<'_>,
+    ) -> Result<Option<CounterHandle>> {
+        // A counter that fails to open inside a group still counts as a member of that group and
+        // breaks reading it, so each counter is first probed on its own.
+        let (counting_mode, include_hypervisor) = (self.counting_mode, self.include_hypervisor);
+        let mut probe = configure(perf_event::Builder::new());
+        counting_mode.configure(&mut probe, include_hypervisor);
+        let group = probe.build().and_then(|_| self.next_group(hardware));
+        let group = match group {
+            Ok(group) => group,
+            Err(err) => {
+                self.unavailable
+                    .insert(name.to_string(), unavailable_reason(&err));
+                return Ok(None);
+            }
+        };
+
+        let mut builder = configure(perf_event::Builder::new().group(&mut self.groups[group]));
+        let effective_mode = counting_mode.configure(&mut builder, include_hypervisor);
+        let counter = builder
+            .build()
+            .map_err(|err| eyre!("Perf counter `{name}` can't be opened in a group: {err}"))?;
+        if effective_mode != counting_mode {
+            self.effective_counting_modes
+                .insert(name.to_string(), effective_mode);
+        }
+        if hardware {
+            self.hardware_events_in_last_group += 1;
+        }
+        Ok(Some(CounterHandle {
+            name: name.to_string(),
+            group,
+            counter,
+        }))
+    }
+
+    fn add_builtin(&mut self, name: &str) -> Result<Option<CounterHandle>> {
+        let (_, kind) = BUILTIN_EVENTS
+            .iter()
+            .find(|(builtin_name, _)| *builtin_name == name)
+            .expect("built-in perf event");
+        let hardware = !matches!(kind, perf_events::Event::Software(_));
+        self.add(name, hardware, |builder| builder.kind(kind.clone()))
+    }
+
+    fn add_raw(&mut self, raw_event: &RawEventConfig) -> Result<Option<CounterHandle>> {
+        self.add(
+            &raw_event.name,
+            raw_event.event_type != PERF_TYPE_SOFTWARE,
+            |mut builder| {
+                let attrs = builder.attrs_mut();
+                attrs.type_ = raw_event.event_type;
+                attrs.config = raw_event.config;
+                attrs.__bindgen_anon_3.config1 = raw_event.config1;
+                builder
+            },
+        )
+    }
+}
+
+/// `handle` or its copy in `shared_inputs` that was opened in `group`.
+#[cfg(feature = "perf")]
+fn find_in_group<'a>(
+    shared_inputs: &'a [CounterHandle],
+    handle: &'a CounterHandle,
+    group: usize,
+) -> Option<&'a CounterHandle> {
+    if handle.group == group {
+        return Some(handle);
+    }
+    shared_inputs
+        .iter()
+        .find(|copy| copy.name == handle.name && copy.group == group)
+}
+
+/// Instances of `a` and `b`, each the counter itself or a copy in `shared_inputs`, that were opened
+/// in the same group.
+#[cfg(feature = "perf")]
+fn same_group<'a>(
+    shared_inputs: &'a [CounterHandle],
+    a: &'a CounterHandle,
+    b: &'a CounterHandle,
+) -> Option<(&'a CounterHandle, &'a CounterHandle)> {
+    find_in_group(shared_inputs, b, a.group)
+        .map(|b| (a, b))
+        .or_else(|| find_in_group(shared_inputs, a, b.group).map(|a| (a, b)))
+}
+
+/// Explains why a counter couldn't be opened, translating the errno of `perf_event_open`.
+#[cfg(feature = "perf")]
+pub(crate) fn unavailable_reason(err: &io::Error) -> String {
+    match err.raw_os_error() {
+        Some(libc::EACCES | libc::EPERM) => match perf_event_paranoid() {
+            Some(level) => format!(
+                "permission denied: perf_event_paranoid is {level}, counting needs 2 or lower for \
+                 user space, 1 or lower for kernel space, or CAP_PERFMON"
+            ),
+            None => {
+                "permission denied: perf_event_paranoid forbids access or CAP_PERFMON is missing"
+                    .to_string()
+            }
+        },
+        Some(libc::ENOENT | libc::ENODEV) => {
+            "not supported by this CPU or kernel, e.g. in a VM or container without PMU access"
+                .to_string()
+        }
+        Some(libc::EOPNOTSUPP) => "not supported by the PMU".to_string(),
+        Some(libc::EINVAL) => "invalid event for this CPU".to_string(),
+        Some(libc::ENOSYS) => {
+            "perf_event_open is not available, e.g. blocked by seccomp".to_string()
+        }
+        _ => err.to_string(),
+    }
+}
+
+/// The `kernel.perf_event_paranoid` sysctl, if it can be read.
+pub fn perf_event_paranoid() -> Option<i32> {
+    std::fs::read_to_string("/proc/sys/kernel/perf_event_paranoid")
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Whether a counter works on the current machine.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerfEventAvailability {
+    pub name: String,
+    /// Why the counter can't be used, `None` if it works.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unavailable_reason: Option<String>,
+}
+
+/// Probes every built-in and named event on its own by opening, enabling and reading it on the
+/// calling thread, in the given counting mode.
+#[cfg(feature = "perf")]
+pub fn check_perf_events(
+    counting_mode: CountingMode,
+    include_hypervisor: bool,
+) -> Vec<PerfEventAvailability> {
+    let named_events: Vec<_> = RawEventConfig::names()
+        .filter_map(RawEventConfig::named)
+        .collect();
+
+    let builtin_names = BUILTIN_EVENTS.iter().map(|(name, _)| *name);
+    let named_names = named_events.iter().map(|raw_event| raw_event.name.as_str());
+    builtin_names
+        .chain(named_names)
+        .map(|name| {
+            let mut allocator = GroupAllocator::new(1, counting_mode, include_hypervisor);
+            let handle = match named_events.iter().find(|raw_event| raw_event.name == name) {
+                Some(raw_event) => allocator.add_raw(raw_event),
+                None => allocator.add_builtin(name),
+            };
+            let unavailable_reason = match handle {
+                Ok(Some(handle)) => {
+                    probe_counter(&mut allocator.groups[handle.group], &handle).err()
+                }
+                Ok(None) => allocator.unavailable.remove(name),
+                Err(err) => Some(err.to_string()),
+            };
+            PerfEventAvailability {
+                name: name.to_string(),
+                unavailable_reason,
+            }
+        })
+        .collect()
+}
+
+/// Without the `perf` feature there are no counters to probe.
+#[cfg(not(feature = "perf"))]
+pub fn check_perf_events(
+    _counting_mode: CountingMode,
+    _include_hypervisor: bool,
+) -> Vec<PerfEventAvailability> {
+    Vec::new()
+}
+
+#[cfg(feature = "perf")]
+fn probe_counter(group: &mut perf_event::Group, handle: &CounterHandle) -> Result<(), String> {
+    let counts = group
+        .enable()
+        .and_then(|_| group.disable())
+        .and_then(|_| group.read())
+        .map_err(|err| unavailable_reason(&err))?;
+    scale_count(
+        counts[&handle.counter],
+        counts.time_enabled(),
+        counts.time_running(),
+        MultiplexingPolicy::Scale,
+    )?
+    .map(|_| ())
+    .ok_or_else(|| NEVER_SCHEDULED.to_string())
+}
+
+#[cfg(feature = "perf")]
+const NEVER_SCHEDULED: &str =
+    "was never scheduled on the PMU, request fewer events or fewer hardware events per group";
+
+#[cfg(feature = "perf")]
+pub(crate) struct PerfEventCollector {
+    groups: Vec<perf_event::Group>,
+    multiplexing: MultiplexingPolicy,
+    cycles: Option<CounterHandle>,
+    instructions: Option<CounterHandle>,
+    last_level_cache_references: Option<CounterHandle>,
+    last_level_cache_misses: Option<CounterHandle>,
+    l1_data_cache_reads: Option<CounterHandle>,
+    l1_data_cache_misses: Option<CounterHandle>,
+    l1_instruction_cache_misses: Option<CounterHandle>,
+    branch_instructions: Option<CounterHandle>,
+    branch_misses: Option<CounterHandle>,
+    cpu_migrations: Option<CounterHandle>,
+    instruction_tlb_misses: Option<CounterHandle>,
+    data_tlb_reads: Option<CounterHandle>,
+    data_tlb_misses: Option<CounterHandle>,
+    page_faults_minor: Option<CounterHandle>,
+    page_faults_major: Option<CounterHandle>,
+    context_switches: Option<CounterHandle>,
+    task_clock: Option<CounterHandle>,
+    stalled_cycles_frontend: Option<CounterHandle>,
+    stalled_cycles_backend: Option<CounterHandle>,
+    ref_cycles: Option<CounterHandle>,
+    raw_events: Vec<CounterHandle>,
+    /// See [`GroupAllocator::shared_inputs`].
+    shared_inputs: Vec<CounterHandle>,
+    /// Counters that couldn't be opened, with the reason.
+    unavailable: BTreeMap<String, String>,
+    effective_counting_modes: BTreeMap<String, CountingMode>,
+    /// Thread resource usage when the groups were enabled, used for the voluntary/involuntary split
+    /// of context switches which perf events don't provide.
+    rusage_at_enable: Option<libc::rusage>,
+}
+
+#[cfg(feature = "perf")]
+impl PerfEventCollector {
+    pub(crate) fn new(config: &PerfEventConfig) -> Result<Self> {
+        let mut allocator = GroupAllocator::new(
+            config
+                .max_hardware_events_per_group
+                .unwrap_or(DEFAULT_MAX_HARDWARE_EVENTS_PER_GROUP),
+            config.counting_mode,
+            config.include_hypervisor,
+        );
+
+        macro_rules! optional_perf_event {
+            ($enabled:expr, $name:expr) => {
+                if $enabled {
+                    allocator.add_builtin($name)?
+                } else {
+                    None
+                }
+            };
+        }
+
+        macro_rules! optional_perf_event_next_to {
+            ($enabled:expr, $name:expr, $input:expr) => {
+                if $enabled {
+                    allocator.add_next_to($name, $input.as_ref(), GroupAllocator::add_builtin)?
+                } else {
+                    None
+                }
+            };
+        }
+
+        // Makes room for both counters of a derived metric in the last group, so that neither has
+        // to be opened twice.
+        macro_rules! reserve_pair {
+            ($first:expr, $second:expr) => {
+                if $first && $second {
+                    allocator.reserve_hardware_events(2)?;
+                }
+            };
+        }
+
+        let cycles = optional_perf_event!(config.cycles, "cycles");
+        let instructions =
+            optional_perf_event_next_to!(config.instructions, "instructions", cycles);
+        let ref_cycles = optional_perf_event_next_to!(config.ref_cycles, "ref_cycles", cycles);
+        let stalled_cycles_frontend = optional_perf_event_next_to!(
+            config.stalled_cycles_frontend,
+            "stalled_cycles_frontend",
+            cycles
+        );
+        let stalled_cycles_backend = optional_perf_event_next_to!(
+            config.stalled_cycles_backend,
+            "stalled_cycles_backend",
+            cycles
+        );
+        reserve_pair!(
+            config.last_level_cache_references,
+            config.last_level_cache_misses
+        );
+        let last_level_cache_references = optional_perf_event!(
+            config.last_level_cache_references,
+            "last_level_cache_references"
+        );
+        let last_level_cache_misses = optional_perf_event_next_to!(
+            config.last_level_cache_misses,
+            "last_level_cache_misses",
+            last_level_cache_references
+        );
+        reserve_pair!(config.l1_data_cache_reads, config.l1_data_cache_misses);
+        let l1_data_cache_reads =
+            optional_perf_event!(config.l1_data_cache_reads, "l1_data_cache_reads");
+        let l1_data_cache_misses = optional_perf_event_next_to!(
+            config.l1_data_cache_misses,
+            "l1_data_cache_misses",
+            l1_data_cache_reads
+        );
+        let l1_instruction_cache_misses = optional_perf_event!(
+            config.l1_instruction_cache_misses,
+            "l1_instruction_cache_misses"
+        );
+        reserve_pair!(config.branch_instructions, config.branch_misses);
+        let branch_instructions =
+            optional_perf_event!(config.branch_instructions, "branch_instructions");
+        let branch_misses = optional_perf_event_next_to!(
+            config.branch_misses,
+            "branch_misses",
+            branch_instructions
+        );
+        let instruction_tlb_misses = optional_perf_event_next_to!(
+            config.instruction_tlb_misses,
+            "instruction_tlb_misses",
+            instructions
+        );
+        reserve_pair!(config.data_tlb_reads, config.data_tlb_misses);
+        let data_tlb_reads = optional_perf_event!(config.data_tlb_reads, "data_tlb_reads");
+        let data_tlb_misses =
+            optional_perf_event_next_to!(config.data_tlb_misses, "data_tlb_misses", data_tlb_reads);
+        let cpu_migrations = optional_perf_event!(config.cpu_migrations, "cpu_migrations");
+        let page_faults_minor = optional_perf_event!(config.page_faults_minor, "page_faults_minor");
+        let page_faults_major = optional_perf_event!(config.page_faults_major, "page_faults_major");
+        let context_switches = optional_perf_event!(config.context_switches, "context_switches");
+        let task_clock = optional_perf_event!(config.task_clock, "task_clock");
+
+        let named_events = config
+            .named_events
+            .iter()
+            .map(|name| {
+                RawEventConfig::named(name)
+                    .ok_or_else(|| eyre!("Unknown named perf event `{name}`"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // Counts are reported by name, so an event with a duplicate name would replace another one.
+        let mut names: BTreeSet<&str> = BUILTIN_EVENTS.iter().map(|(name, _)| *name).collect();
+        for raw_event in config.raw_events.iter().chain(named_events.iter()) {
+            if !names.insert(&raw_event.name) {
+                bail!(
+                    "Perf event name `{}` is used more than once",
+                    raw_event.name
+                );
+            }
+        }
+        let raw_events = config
+            .raw_events
+            .iter()
+            .chain(named_events.iter())
+            .filter_map(|raw_event| allocator.add_raw(raw_event).transpose())
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            groups: allocator.groups,
+            multiplexing: config.multiplexing,
+            cycles,
+            instructions,
+            last_level_cache_references,
+            last_level_cache_misses,
+            l1_data_cache_reads,
+            l1_data_cache_misses,
+            l1_instruction_cache_misses,
+            branch_instructions,
+            branch_misses,
+            cpu_migrations,
+            instruction_tlb_misses,
+            data_tlb_reads,
+            data_tlb_misses,
+            page_faults_minor,
+            page_faults_major,
+            context_switches,
+            task_clock,
+            stalled_cycles_frontend,
+            stalled_cycles_backend,
+            ref_cycles,
+            raw_events,
+            shared_inputs: allocator.shared_inputs,
+            unavailable: allocator.unavailable,
+            effective_counting_modes: allocator.effective_counting_modes,
+            rusage_at_enable: None,
+        })
+    }
+
+    pub(crate) fn enable(&mut self) -> Result<()> {
+        if self.context_switches.is_some() {
+            self.rusage_at_enable = Some(thread_rusage()?);
+        }
+        for group in &mut self.groups {
+            group.enable()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn report(&mut self) -> Result<PerfReport> {
+        for group in self.groups.iter_mut().rev() {
+            group.disable()?;
+        }
+        let rusage_at_disable = self.rusage_at_enable.map(|_| thread_rusage()).transpose()?;
+        let counts = self
+            .groups
+            .iter_mut()
+            .map(perf_event::Group::read)
+            .collect::<io::Result<Vec<_>>>()?;
+        let mut running_ratios = BTreeMap::new();
+        let mut unavailable = self.unavailable.clone();
+
+        let mut read_counter = |handle: &CounterHandle| -> Result<Option<u64>> {
+            let group_counts = &counts[handle.group];
+            let scaled = scale_count(
+                group_counts[&handle.counter],
+                group_counts.time_enabled(),
+                group_counts.time_running(),
+                self.multiplexing,
+            )
+            .map_err(|err| eyre!("Perf counter `{}` {err}", handle.name))?;
+            match scaled {
+                Some((count, running_ratio)) => {
+                    running_ratios.insert(handle.name.clone(), running_ratio);
+                    Ok(Some(count))
+                }
+                None => {
+                    unavailable.insert(handle.name.clone(), NEVER_SCHEDULED.to_string());
+                    Ok(None)
+                }
+            }
+        };
+
+        // Derived metrics divide the raw counts of one group, which were scheduled over the same
+        // time windows. Only if the inputs couldn't share a group are the extrapolated counts used.
+        let ratio = |numerator: &Option<CounterHandle>,
+                     numerator_count: Option<u64>,
+                     denominator: &Option<CounterHandle>,
+                     denominator_count: Option<u64>| {
+            let (mut numerator_count, mut denominator_count) =
+                (numerator_count?, denominator_count?);
+            if let Some((numerator, denominator)) = same_group(
+                &self.shared_inputs,
+                numerator.as_ref()?,
+                denominator.as_ref()?,
+            ) {
+                numerator_count = counts[numerator.group][&numerator.counter];
+                denominator_count = counts[denominator.group][&denominator.counter];
+            }
+            (denominator_count != 0).then(|| numerator_count as f64 / denominator_count as f64)
+        };
+
+        macro_rules! read_count {
+            ($counter:expr) => {
+                match $counter.as_ref() {
+                    Some(handle) => read_counter(handle)?,
+                    None => None,
+                }
+            };
+        }
+
+        let instructions = read_count!(&self.instructions);
+        let cycles = read_count!(&self.cycles);
+        let instructions_per_cycle = ratio(&self.instructions, instructions, &self.cycles, cycles);
+
+        let last_level_cache_misses = read_count!(&self.last_level_cache_misses);
+        let last_level_cache_references = read_count!(&self.last_level_cache_references);
+        let last_level_cache_hit_rate = ratio(
+            &self.last_level_cache_misses,
+            last_level_cache_misses,
+            &self.last_level_cache_references,
+            last_level_cache_references,
+        )
+        .map(|miss_rate| 1.0 - miss_rate);
+
+        let l1_data_cache_misses = read_count!(&self.l1_data_cache_misses);
+        let l1_data_cache_reads = read_count!(&self.l1_data_cache_reads);
+        let l1_data_cache_hit_rate = ratio(
+            &self.l1_data_cache_misses,
+            l1_data_cache_misses,
+            &self.l1_data_cache_reads,
+            l1_data_cache_reads,
+        )
+        .map(|miss_rate| 1.0 - miss_rate);
+
+        let l1_instruction_cache_misses = read_count!(&self.l1_instruction_cache_misses);
+
+        let branch_misses = read_count!(&self.branch_misses);
+        let branch_instructions = read_count!(&self.branch_instructions);
+        let branch_miss_ratio = ratio(
+            &self.branch_misses,
+            branch_misses,
+            &self.branch_instructions,
+            branch_instructions,
+        );
+
+        let cpu_migrations = read_count!(&self.cpu_migrations);
+
+        let instruction_tlb_misses = read_count!(&self.instruction_tlb_misses);
+        let instruction_tlb_misses_per_kilo_instruction = ratio(
+            &self.instruction_tlb_misses,
+            instruction_tlb_misses,
+            &self.instructions,
+            instructions,
+        )
+        .map(|ratio| ratio * 1000.0);
+
+        let data_tlb_misses = read_count!(&self.data_tlb_misses);
+        let data_tlb_reads = read_count!(&self.data_tlb_reads);
+        let data_tlb_miss_ratio = ratio(
+            &self.data_tlb_misses,
+            data_tlb_misses,
+            &self.data_tlb_reads,
+            data_tlb_reads,
+        );
+
+        let page_faults_minor = read_count!(&self.page_faults_minor);
+        let page_faults_major = read_count!(&self.page_faults_major);
+
+        let context_switches = read_count!(&self.context_switches);
+        let (voluntary_context_switches, involuntary_context_switches) =
+            match (self.rusage_at_enable, rusage_at_disable) {
+                (Some(start), Some(end)) => (
+                    Some((end.ru_nvcsw - start.ru_nvcsw) as u64),
+                    Some((end.ru_nivcsw - start.ru_nivcsw) as u64),
+                ),
+                _ => (None, None),
+            };
+
+        let task_clock_ns = read_count!(&self.task_clock);
+        let time_enabled = self
+            .task_clock
+            .as_ref()
+            .map(|handle| counts[handle.group].time_enabled() as f64);
+        let cpu_utilization = match (task_clock_ns, time_enabled) {
+            (Some(task_clock), Some(time_enabled)) if time_enabled != 0.0 => {
+                Some(task_clock as f64 / time_enabled)
+            }
+            _ => None,
+        };
+
+        let stalled_cycles_frontend = read_count!(&self.stalled_cycles_frontend);
+        let stalled_cycles_frontend_ratio = ratio(
+            &self.stalled_cycles_frontend,
+            stalled_cycles_frontend,
+            &self.cycles,
+            cycles,
+        );
+        let stalled_cycles_backend = read_count!(&self.stalled_cycles_backend);
+        let stalled_cycles_backend_ratio = ratio(
+            &self.stalled_cycles_backend,
+            stalled_cycles_backend,
+            &self.cycles,
+            cycles,
+        );
+
+        let ref_cycles = read_count!(&self.ref_cycles);
+        let cycles_per_ref_cycle = ratio(&self.cycles, cycles, &self.ref_cycles, ref_cycles);
+
+        let counters = self
+            .raw_events
+            .iter()
+            .filter_map(|handle| {
+                read_counter(handle)
+                    .transpose()
+                    .map(|count| Ok((handle.name.clone(), count?)))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        Ok(PerfReport {
+            instructions,
+            instructions_per_cycle,
+            last_level_cache_hit_rate,
+            l1_data_cache_hit_rate,
+            l1_instruction_cache_misses,
+            branch_miss_ratio,
+            cpu_migrations,
+            instruction_tlb_misses,
+            instruction_tlb_misses_per_kilo_instruction,
+            data_tlb_miss_ratio,
+            page_faults_minor,
+            page_faults_major,
+            context_switches,
+            voluntary_context_switches,
+            involuntary_context_switches,
+            task_clock_ns,
+            cpu_utilization,
+            stalled_cycles_frontend_ratio,
+            stalled_cycles_backend_ratio,
+            ref_cycles,
+            cycles_per_ref_cycle,
+            counters,
+            running_ratios,
+            unavailable,
+            effective_counting_modes: self.effective_counting_modes.clone(),
+        })
+    }
+}
+
+/// Stand-in for builds without the `perf` feature, which can't be constructed.
+#[cfg(not(feature = "perf"))]
+pub(crate) struct PerfEventCollector(std::convert::Infallible);
+
+#[cfg(not(feature = "perf"))]
+impl PerfEventCollector {
+    pub(crate) fn new(_config: &PerfEventConfig) -> Result<Self> {
+        Err(eyre!("Perf counters need the `perf` feature"))
+    }
+
+    pub(crate) fn enable(&mut self) -> Result<()> {
+        match self.0 {}
+    }
+
+    pub(crate) fn report(&mut self) -> Result<PerfReport> {
+        match self.0 {}
+    }
+}
+
+/// Extrapolate a count of a group that was running for `time_running` out of `time_enabled`
+/// nanoseconds, returning the scaled count and the running ratio, or `None` if it never ran.
+#[cfg(feature = "perf")]
+fn scale_count(
+    count: u64,
+    time_enabled: u64,
+    time_running: u64,
+    multiplexing: MultiplexingPolicy,
+) -> Result<Option<(u64, f64)>, String> {
+    if time_running == 0 {
+        return Ok(None);
+    }
+
+    let running_ratio = time_running as f64 / time_enabled as f64;
+    if running_ratio < 1.0 && multiplexing == MultiplexingPolicy::Reject {
+        return Err(format!(
+            "was multiplexed and only counting for {:.1}% of the time",
+            running_ratio * 100.0
+        ));
+    }
+
+    // Scaled in integers so that counts above 2^53 stay exact.
+    let scaled = u128::from(count) * u128::from(time_enabled) / u128::from(time_running);
+    let scaled = u64::try_from(scaled).map_err(|_| "overflowed when scaled".to_string())?;
+    Ok(Some((scaled, running_ratio)))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerfReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions_per_cycle: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_level_cache_hit_rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l1_data_cache_hit_rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l1_instruction_cache_misses: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch_miss_ratio: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_migrations: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instruction_tlb_misses: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instruction_tlb_misses_per_kilo_instruction: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_tlb_miss_ratio: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_faults_minor: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page_faults_major: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_switches: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub voluntary_context_switches: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub involuntary_context_switches: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_clock_ns: Option<u64>,
+    /// Task clock divided by the time the counters were enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_utilization: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stalled_cycles_frontend_ratio: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stalled_cycles_backend_ratio: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ref_cycles: Option<u64>,
+    /// Cycles divided by reference cycles: above 1 means turbo, below 1 means down-clocking.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycles_per_ref_cycle: Option<f64>,
+    /// Counts of the requested raw and named events by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub counters: BTreeMap<String, u64>,
+    /// Share of the enabled time each counter was actually counting, by counter name. Below 1.0
+    /// means the counter was multiplexed and its count was extrapolated.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub running_ratios: BTreeMap<String, f64>,
+    /// Requested counters that couldn't be collected on this machine, by counter name, with the
+    /// reason. Metrics derived from them are left out of the report.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub unavailable: BTreeMap<String, String>,
+    /// Counters that the kernel counts in a different mode than the requested [`CountingMode`],
+    /// by counter name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub effective_counting_modes: BTreeMap<String, CountingMode>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derived_metrics_enable_their_inputs() {
+        let config: PerfEventConfig = PerfReportConfig {
+            instruction_tlb_misses: true,
+            stalled_cycles: true,
+            ..PerfReportConfig::default()
+        }
+        .into();
+
+        assert!(config.instruction_tlb_misses && config.instructions);
+        assert!(config.stalled_cycles_frontend && config.stalled_cycles_backend && config.cycles);
+        assert!(!config.ref_cycles);
+        assert!(PerfReportConfig::default().is_empty());
+    }
+
+    #[test]
+    fn test_parse_raw_event() {
+        assert_eq!(
+            "l1i_miss=4:0x1c6:0x12".parse(),
+            Ok(RawEventConfig {
+                name: "l1i_miss".to_string(),
+                event_type: PERF_TYPE_RAW,
+                config: 0x1c6,
+                config1: 0x12,
+            })
+        );
+        assert_eq!(
+            "frontend_retired.l1i_miss=4:1c6:12".parse::<RawEventConfig>(),
+            Ok(RawEventConfig::named("frontend_retired.l1i_miss").unwrap())
+        );
+        assert!("l1i_miss=4".parse::<RawEventConfig>().is_err());
+        assert!("l1i_miss=4:xyz".parse::<RawEventConfig>().is_err());
+    }
+
+    #[cfg(feature = "perf")]
+    #[test]
+    fn test_duplicate_event_names_are_rejected() {
+        let config =
+            |raw_events: Vec<RawEventConfig>, named_events: Vec<&str>| -> PerfEventConfig {
+                PerfReportConfig {
+                    raw_events,
+                    named_events: named_events.into_iter().map(str::to_string).collect(),
+                    ..PerfReportConfig::default()
+                }
+                .into()
+            };
+        let raw_event = |name: &str| RawEventConfig {
+            name: name.to_string(),
+            ..RawEventConfig::named("idq.dsb_uops").unwrap()
+        };
+
+        for config in [
+            config(vec![raw_event("dsb"), raw_event("dsb")], vec![]),
+            config(vec![raw_event("idq.dsb_uops")], vec!["idq.dsb_uops"]),
+            config(vec![], vec!["idq.dsb_uops", "idq.dsb_uops"]),
+            config(vec![raw_event("cycles")], vec![]),
+        ] {
+            let err = PerfEventCollector::new(&config)
+                .err()
+                .expect("duplicate name is rejected");
+            assert!(err.to_string().contains("more than once"), "{err}");
+        }
+    }
+
+    #[cfg(feature = "perf")]
+    #[test]
+    fn test_scale_multiplexed_count() {
+        assert_eq!(
+            scale_count(100, 1_000, 1_000, MultiplexingPolicy::Reject),
+            Ok(Some((100, 1.0)))
+        );
+        assert_eq!(
+            scale_count(100, 1_000, 250, MultiplexingPolicy::Scale),
+            Ok(Some((400, 0.25)))
+        );
+        assert!(scale_count(100, 1_000, 250, MultiplexingPolicy::Reject).is_err());
+        assert_eq!(
+            scale_count(0, 1_000, 0, MultiplexingPolicy::Scale),
+            Ok(None)
+        );
+    }
+
+    #[cfg(feature = "perf")]
+    #[test]
+    fn test_derived_metric_inputs_share_a_group() -> Result<()> {
+        // Software events stand in for hardware ones, which aren't available in every environment.
+        fn add_as_hardware(
+            allocator: &mut GroupAllocator,
+            name: &str,
+        ) -> Result<Option<CounterHandle>> {
+            allocator.add(name, true, |builder| {
+                builder.kind(perf_events::Software::DUMMY)
+            })
+        }
+
+        let mut allocator = GroupAllocator::new(3, CountingMode::User, false);
+        let cycles = allocator.add_next_to("cycles", None, add_as_hardware)?;
+        let mut pairs = Vec::new();
+        for name in [
+            "instructions",
+            "ref_cycles",
+            "stalled_cycles_frontend",
+            "stalled_cycles_backend",
+        ] {
+            pairs.push((
+                "cycles",
+                name,
+                allocator.add_next_to(name, cycles.as_ref(), add_as_hardware)?,
+            ));
+        }
+        allocator.reserve_hardware_events(2)?;
+        let data_tlb_reads = allocator.add_next_to("data_tlb_reads", None, add_as_hardware)?;
+        let data_tlb_misses =
+            allocator.add_next_to("data_tlb_misses", data_tlb_reads.as_ref(), add_as_hardware)?;
+
+        let cycles = cycles.expect("cycles can be opened");
+        for (input, name, handle) in &pairs {
+            let handle = handle.as_ref().expect("counter can be opened");
+            assert!(
+                same_group(&allocator.shared_inputs, &cycles, handle).is_some(),
+                "{name} is not next to {input}"
+            );
+        }
+        let (data_tlb_reads, data_tlb_misses) = (data_tlb_reads.unwrap(), data_tlb_misses.unwrap());
+        assert_eq!(data_tlb_reads.group, data_tlb_misses.group);
+
+        let mut events_per_group = vec![0; allocator.groups.len()];
+        let handles = pairs.iter().filter_map(|(_, _, handle)| handle.as_ref());
+        for handle in handles.chain(&allocator.shared_inputs).chain([
+            &cycles,
+            &data_tlb_reads,
+            &data_tlb_misses,
+        ]) {
+            events_per_group[handle.group] += 1;
+        }
+        assert!(
+            events_per_group.iter().all(|&count| count <= 3),
+            "{events_per_group:?}"
+        );
+        assert_eq!(allocator.shared_inputs.len(), 1);
+        Ok(())
+    }
+
+    #[cfg(feature = "perf")]
+    #[test]
+    fn test_context_switches_are_counted_in_user_mode() -> Result<()> {
+        let config: PerfEventConfig = PerfReportConfig {
+            context_switches: true,
+            ..PerfReportConfig::default()
+        }
+        .into();
+        assert_eq!(config.counting_mode, CountingMode::User);
+        let mut collector = PerfEventCollector::new(&config)?;
+        collector.enable()?;
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        let report = collector.report()?;
+
+        // Like the other software events, context switches can be counted wherever perf events
+        // can be opened at all.
+        assert!(
+            report.context_switches.is_some_and(|count| count > 0),
+            "{report:?}"
+        );
+        assert_eq!(
+            report.effective_counting_modes.get("context_switches"),
+            Some(&CountingMode::Kernel)
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "perf")]
+    #[test]
+    fn test_counting_mode_applies_to_software_events_where_possible() {
+        use perf_events::Software;
+
+        let configure = |kind: Software, mode: CountingMode| {
+            let mut builder = perf_event::Builder::new().kind(kind);
+            let effective_mode = mode.configure(&mut builder, false);
+            let attrs = builder.attrs();
+            (
+                effective_mode,
+                attrs.exclude_user() == 1,
+                attrs.exclude_kernel() == 1,
+            )
+        };
+
+        for mode in [
+            CountingMode::User,
+            CountingMode::Kernel,
+            CountingMode::UserAndKernel,
+        ] {
+            assert_eq!(
+                configure(Software::PAGE_FAULTS_MIN, mode),
+                (
+                    mode,
+                    mode == CountingMode::Kernel,
+                    mode == CountingMode::User
+                )
+            );
+            assert_eq!(
+                configure(Software::TASK_CLOCK, mode).0,
+                CountingMode::UserAndKernel
+            );
+        }
+        assert_eq!(
+            configure(Software::CONTEXT_SWITCHES, CountingMode::User),
+            (CountingMode::Kernel, true, false)
+        );
+        assert_eq!(
+            configure(Software::CPU_MIGRATIONS, CountingMode::Kernel),
+            (CountingMode::Kernel, true, false)
+        );
+    }
+
+    #[cfg(feature = "perf")]
+    #[test]
+    fn test_check_perf_events_covers_every_event() {
+        let availability = check_perf_events(CountingMode::default(), false);
+
+        assert_eq!(
+            availability.len(),
+            BUILTIN_EVENTS.len() + RawEventConfig::names().count()
+        );
+        for event in &availability {
+            if let Some(reason) = &event.unavailable_reason {
+                assert!(!reason.is_empty(), "{}", event.name);
+            }
+        }
+    }
+}
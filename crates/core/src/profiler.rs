@@ -0,0 +1,394 @@
+use crate::perf::{RawEventConfig, PERF_TYPE_HARDWARE, PERF_TYPE_SOFTWARE};
+use eyre::{eyre, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+#[cfg(feature = "perf")]
+use {
+    crate::perf::unavailable_reason,
+    perf_event_open_sys::{self as sys, bindings},
+    std::{
+        collections::{BTreeMap, HashMap},
+        ffi::c_void,
+        fs::File,
+        io::{self, BufWriter, Write},
+        mem,
+        os::fd::{AsRawFd, FromRawFd, OwnedFd},
+        ptr, slice,
+        sync::atomic::{fence, Ordering},
+    },
+};
+
+/// Samples per second when not configured.
+pub const DEFAULT_SAMPLING_FREQUENCY: u64 = 10_000;
+
+/// Data pages of the sample ring buffer, a power of two. 128 pages stay within the default
+/// `perf_event_mlock_kb` limit of unprivileged users.
+#[cfg(feature = "perf")]
+const RING_BUFFER_PAGES: usize = 128;
+
+/// Samples the calling thread's user-space call stacks while the REVM transaction runs.
+///
+/// Call chains are walked by the kernel using frame pointers, so build with
+/// `RUSTFLAGS="-C force-frame-pointers=yes"` to get complete stacks.
+#[derive(Clone, Debug)]
+pub struct SamplingConfig {
+    /// Event to sample on, see [`SamplingConfig::parse_event`].
+    pub event: RawEventConfig,
+    /// Samples per second of the sampled event's time, [`DEFAULT_SAMPLING_FREQUENCY`] if not set.
+    pub frequency: Option<u64>,
+    /// Where to write the folded stacks, one `root;...;leaf count` line per unique stack.
+    pub folded_path: PathBuf,
+    /// Where to write an SVG flamegraph of the folded stacks.
+    pub flamegraph_path: Option<PathBuf>,
+}
+
+impl SamplingConfig {
+    /// Parses `cycles`, `instructions`, `cpu-clock`, `task-clock`, an event from the named event
+    /// table or a raw event as `name=type:config[:config1]`.
+    pub fn parse_event(event: &str) -> Result<RawEventConfig, String> {
+        let generic = |event_type, config| RawEventConfig {
+            name: event.to_string(),
+            event_type,
+            config,
+            config1: 0,
+        };
+
+        match event {
+            // `PERF_COUNT_HW_CPU_CYCLES`, `PERF_COUNT_HW_INSTRUCTIONS`, `PERF_COUNT_SW_CPU_CLOCK`
+            // and `PERF_COUNT_SW_TASK_CLOCK`.
+            "cycles" => Ok(generic(PERF_TYPE_HARDWARE, 0)),
+            "instructions" => Ok(generic(PERF_TYPE_HARDWARE, 1)),
+            "cpu-clock" => Ok(generic(PERF_TYPE_SOFTWARE, 0)),
+            "task-clock" => Ok(generic(PERF_TYPE_SOFTWARE, 1)),
+            _ => RawEventConfig::named(event).map_or_else(|| event.parse(), Ok),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileReport {
+    /// Samples taken during the REVM transaction.
+    pub samples: u64,
+    /// Samples dropped because the ring buffer was full.
+    pub lost_samples: u64,
+    pub folded_path: PathBuf,
+    /// Path of the SVG flamegraph, only written if samples were taken.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flamegraph_path: Option<PathBuf>,
+}
+
+#[cfg(feature = "perf")]
+pub(crate) struct Sampler {
+    config: SamplingConfig,
+    fd: OwnedFd,
+    ring_buffer: *mut c_void,
+    ring_buffer_len: usize,
+}
+
+#[cfg(feature = "perf")]
+impl Sampler {
+    pub(crate) fn new(config: SamplingConfig) -> Result<Self> {
+        let mut attrs = bindings::perf_event_attr {
+            size: mem::size_of::<bindings::perf_event_attr>() as u32,
+            type_: config.event.event_type,
+            config: config.event.config,
+            sample_type: bindings::PERF_SAMPLE_CALLCHAIN as u64,
+            ..bindings::perf_event_attr::default()
+        };
+        attrs.__bindgen_anon_1.sample_freq = config.frequency.unwrap_or(DEFAULT_SAMPLING_FREQUENCY);
+        attrs.__bindgen_anon_3.config1 = config.event.config1;
+        attrs.set_freq(1);
+        attrs.set_disabled(1);
+        attrs.set_exclude_kernel(1);
+        attrs.set_exclude_hv(1);
+        attrs.set_exclude_callchain_kernel(1);
+
+        // SAFETY: `attrs` is a valid, fully initialised attribute struct; pid 0 and cpu -1 sample
+        // the calling thread on any CPU.
+        let fd = unsafe {
+            sys::perf_event_open(&mut attrs, 0, -1, -1, bindings::PERF_FLAG_FD_CLOEXEC as _)
+        };
+        if fd == -1 {
+            let err = io::Error::last_os_error();
+            return Err(eyre!(
+                "Sampling event `{}` can't be opened: {}",
+                config.event.name,
+                unavailable_reason(&err)
+            ));
+        }
+        // SAFETY: `fd` is a freshly opened file descriptor owned by nobody else.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let ring_buffer_len = page_size() * (1 + RING_BUFFER_PAGES);
+        // SAFETY: mapping a perf event fd with a power-of-two number of data pages plus the
+        // metadata page is the documented way to set up its ring buffer.
+        let ring_buffer = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                ring_buffer_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            )
+        };
+        if ring_buffer == libc::MAP_FAILED {
+            return Err(eyre!(
+                "Failed to map the sample ring buffer: {}",
+                io::Error::last_os_error()
+            ));
+        }
+
+        Ok(Self {
+            config,
+            fd,
+            ring_buffer,
+            ring_buffer_len,
+        })
+    }
+
+    pub(crate) fn enable(&mut self) -> Result<()> {
+        // SAFETY: `self.fd` is a valid perf event file descriptor.
+        if unsafe { sys::ioctls::ENABLE(self.fd.as_raw_fd(), 0) } == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn disable(&mut self) -> Result<()> {
+        // SAFETY: `self.fd` is a valid perf event file descriptor.
+        if unsafe { sys::ioctls::DISABLE(self.fd.as_raw_fd(), 0) } == -1 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(())
+    }
+
+    /// Writes the folded stacks and flamegraph of the samples taken while the sampler was
+    /// enabled.
+    pub(crate) fn report(&mut self) -> Result<ProfileReport> {
+        let records = self.read_ring_buffer();
+        let mut symbols = HashMap::new();
+        let mut folded_stacks = BTreeMap::<String, u64>::new();
+        let mut samples = 0;
+        let mut lost_samples = 0;
+
+        for (record_type, body) in parse_records(&records) {
+            match record_type {
+                bindings::PERF_RECORD_SAMPLE => {
+                    samples += 1;
+                    let stack = fold_stack(&callchain(body), &mut symbols);
+                    *folded_stacks.entry(stack).or_default() += 1;
+                }
+                bindings::PERF_RECORD_LOST => {
+                    // struct { u64 id; u64 lost; }
+                    lost_samples += read_u64(body, 1).unwrap_or(0);
+                }
+                _ => {}
+            }
+        }
+
+        let lines: Vec<_> = folded_stacks
+            .iter()
+            .map(|(stack, count)| format!("{stack} {count}"))
+            .collect();
+
+        let mut folded_file = BufWriter::new(File::create(&self.config.folded_path)?);
+        for line in &lines {
+            writeln!(folded_file, "{line}")?;
+        }
+        folded_file.flush()?;
+
+        let flamegraph_path = match &self.config.flamegraph_path {
+            Some(path) if !lines.is_empty() => {
+                let mut options = inferno::flamegraph::Options::default();
+                options.title = format!("REVM transaction ({})", self.config.event.name);
+                options.count_name = "samples".to_string();
+                let writer = BufWriter::new(File::create(path)?);
+                inferno::flamegraph::from_lines(
+                    &mut options,
+                    lines.iter().map(String::as_str),
+                    writer,
+                )
+                .map_err(|err| eyre!("Failed to write flamegraph: {err}"))?;
+                Some(path.clone())
+            }
+            _ => None,
+        };
+
+        Ok(ProfileReport {
+            samples,
+            lost_samples,
+            folded_path: self.config.folded_path.clone(),
+            flamegraph_path,
+        })
+    }
+
+    /// Copies the unread part of the ring buffer out and marks it as read.
+    fn read_ring_buffer(&mut self) -> Vec<u8> {
+        let page_size = page_size();
+        let metadata = self.ring_buffer as *mut bindings::perf_event_mmap_page;
+
+        // SAFETY: the first page of the mapping is the kernel-maintained metadata page and the
+        // data area described by it lies within the mapping. `data_head` has to be read before
+        // the data it covers, hence the acquire fence.
+        unsafe {
+            let head = ptr::read_volatile(ptr::addr_of!((*metadata).data_head));
+            fence(Ordering::Acquire);
+            let tail = (*metadata).data_tail;
+            let (data_offset, data_size) = match (*metadata).data_size {
+                0 => (page_size, RING_BUFFER_PAGES * page_size),
+                size => ((*metadata).data_offset as usize, size as usize),
+            };
+            let data =
+                slice::from_raw_parts((self.ring_buffer as *const u8).add(data_offset), data_size);
+
+            let len = (head - tail) as usize;
+            let start = tail as usize % data_size;
+            let mut records = Vec::with_capacity(len);
+            let first = len.min(data_size - start);
+            records.extend_from_slice(&data[start..start + first]);
+            records.extend_from_slice(&data[..len - first]);
+
+            fence(Ordering::Release);
+            ptr::write_volatile(ptr::addr_of_mut!((*metadata).data_tail), head);
+            records
+        }
+    }
+}
+
+#[cfg(feature = "perf")]
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        // SAFETY: the mapping was created in `Sampler::new` with this length and is unmapped once.
+        unsafe { libc::munmap(self.ring_buffer, self.ring_buffer_len) };
+    }
+}
+
+#[cfg(feature = "perf")]
+// SAFETY: the ring buffer mapping is owned by the sampler and only accessed through `&mut self`.
+unsafe impl Send for Sampler {}
+
+/// Stand-in for builds without the `perf` feature, which can't be constructed.
+#[cfg(not(feature = "perf"))]
+pub(crate) struct Sampler(std::convert::Infallible);
+
+#[cfg(not(feature = "perf"))]
+impl Sampler {
+    pub(crate) fn new(_config: SamplingConfig) -> Result<Self> {
+        Err(eyre!("Sampling needs the `perf` feature"))
+    }
+
+    pub(crate) fn enable(&mut self) -> Result<()> {
+        match self.0 {}
+    }
+
+    pub(crate) fn disable(&mut self) -> Result<()> {
+        match self.0 {}
+    }
+
+    pub(crate) fn report(&mut self) -> Result<ProfileReport> {
+        match self.0 {}
+    }
+}
+
+#[cfg(feature = "perf")]
+fn page_size() -> usize {
+    // SAFETY: `sysconf` has no preconditions.
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// Splits the ring buffer contents into `(type, body)` records.
+#[cfg(feature = "perf")]
+fn parse_records(mut records: &[u8]) -> impl Iterator<Item = (u32, &[u8])> {
+    std::iter::from_fn(move || {
+        // struct perf_event_header { u32 type; u16 misc; u16 size; }
+        let header_len = mem::size_of::<bindings::perf_event_header>();
+        if records.len() < header_len {
+            return None;
+        }
+        let record_type = u32::from_ne_bytes(records[0..4].try_into().ok()?);
+        let size = u16::from_ne_bytes(records[6..8].try_into().ok()?) as usize;
+        if size < header_len || size > records.len() {
+            return None;
+        }
+        let body = &records[header_len..size];
+        records = &records[size..];
+        Some((record_type, body))
+    })
+}
+
+#[cfg(feature = "perf")]
+fn read_u64(body: &[u8], index: usize) -> Option<u64> {
+    let bytes = body.get(index * 8..(index + 1) * 8)?;
+    Some(u64::from_ne_bytes(bytes.try_into().ok()?))
+}
+
+/// The instruction pointers of a sample's call chain, leaf first, without context markers.
+#[cfg(feature = "perf")]
+fn callchain(body: &[u8]) -> Vec<u64> {
+    // struct { u64 nr; u64 ips[nr]; }
+    let nr = read_u64(body, 0).unwrap_or(0) as usize;
+    (1..=nr)
+        .map_while(|index| read_u64(body, index))
+        .filter(|&ip| ip < bindings::PERF_CONTEXT_MAX)
+        .collect()
+}
+
+/// Symbolises a call chain into a `root;...;leaf` folded stack, caching symbols by address.
+#[cfg(feature = "perf")]
+fn fold_stack(callchain: &[u64], symbols: &mut HashMap<u64, Vec<String>>) -> String {
+    let mut frames = Vec::new();
+    for (depth, &ip) in callchain.iter().enumerate() {
+        // Return addresses point after the call, which may already belong to the next line or an
+        // inlined function, so callers are looked up one byte earlier.
+        let address = if depth == 0 { ip } else { ip.saturating_sub(1) };
+        let names = symbols.entry(address).or_insert_with(|| {
+            let mut names = Vec::new();
+            backtrace::resolve(address as *mut c_void, |symbol| {
+                if let Some(name) = symbol.name() {
+                    names.push(format!("{name:#}").replace(';', ":"));
+                }
+            });
+            if names.is_empty() {
+                names.push(format!("{address:#x}"));
+            }
+            names
+        });
+        // Inlined frames are resolved innermost first, matching the leaf-first call chain.
+        frames.extend(names.iter().cloned());
+    }
+    frames.reverse();
+    frames.join(";")
+}
+
+#[cfg(all(test, feature = "perf"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_records_and_callchain() {
+        let mut records = Vec::new();
+        // PERF_RECORD_SAMPLE with a user context marker and two frames.
+        records.extend_from_slice(&bindings::PERF_RECORD_SAMPLE.to_ne_bytes());
+        records.extend_from_slice(&0u16.to_ne_bytes());
+        records.extend_from_slice(&40u16.to_ne_bytes());
+        for value in [3, bindings::PERF_CONTEXT_USER, 0x1000, 0x2000] {
+            records.extend_from_slice(&value.to_ne_bytes());
+        }
+        // PERF_RECORD_LOST with 7 lost samples.
+        records.extend_from_slice(&bindings::PERF_RECORD_LOST.to_ne_bytes());
+        records.extend_from_slice(&0u16.to_ne_bytes());
+        records.extend_from_slice(&24u16.to_ne_bytes());
+        for value in [1u64, 7] {
+            records.extend_from_slice(&value.to_ne_bytes());
+        }
+
+        let parsed: Vec<_> = parse_records(&records).collect();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].0, bindings::PERF_RECORD_SAMPLE);
+        assert_eq!(callchain(parsed[0].1), vec![0x1000, 0x2000]);
+        assert_eq!(parsed[1].0, bindings::PERF_RECORD_LOST);
+        assert_eq!(read_u64(parsed[1].1, 1), Some(7));
+    }
+}
@@ -0,0 +1,325 @@
+use eyre::{bail, Result};
+#[cfg(target_os = "linux")]
+use std::{io, mem};
+
+#[derive(Clone, Debug, Default)]
+pub struct SchedulingConfig {
+    /// CPUs to pin the executing thread to with `sched_setaffinity`.
+    pub cpu_affinity: Option<Vec<usize>>,
+    /// Run the executing thread under `SCHED_FIFO` with this priority (1-99).
+    pub fifo_priority: Option<i32>,
+    /// Nice value of the executing thread.
+    pub nice: Option<i32>,
+}
+
+/// Applies a [`SchedulingConfig`] to the calling thread and restores the previous affinity,
+/// scheduling policy and nice value when dropped, so that pooled threads aren't left pinned.
+#[cfg(target_os = "linux")]
+pub(crate) struct SchedulingGuard {
+    previous_affinity: Option<libc::cpu_set_t>,
+    previous_policy: Option<(libc::c_int, libc::sched_param)>,
+    previous_nice: Option<libc::c_int>,
+}
+
+#[cfg(target_os = "linux")]
+impl SchedulingGuard {
+    pub(crate) fn apply(config: &SchedulingConfig) -> Result<Self> {
+        let mut guard = Self {
+            previous_affinity: None,
+            previous_policy: None,
+            previous_nice: None,
+        };
+
+        if let Some(cpus) = &config.cpu_affinity {
+            let cpu_set = cpu_set(cpus)?;
+            // SAFETY: `cpu_set_t` is a plain bit set for which all zeroes is a valid value.
+            let mut previous: libc::cpu_set_t = unsafe { mem::zeroed() };
+
+            // SAFETY: pid 0 refers to the calling thread and the pointers are valid for the size.
+            check(unsafe {
+                libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut previous)
+            })?;
+            check(unsafe {
+                libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &cpu_set)
+            })?;
+            guard.previous_affinity = Some(previous);
+        }
+
+        if let Some(priority) = config.fifo_priority {
+            // SAFETY: pid 0 refers to the calling thread and `previous_param` is a valid pointer.
+            let previous_policy = unsafe { libc::sched_getscheduler(0) };
+            check(previous_policy)?;
+            let mut previous_param = libc::sched_param { sched_priority: 0 };
+            check(unsafe { libc::sched_getparam(0, &mut previous_param) })?;
+
+            let param = libc::sched_param {
+                sched_priority: priority,
+            };
+            check(unsafe { libc::sched_setscheduler(0, libc::SCHED_FIFO, &param) })?;
+            guard.previous_policy = Some((previous_policy, previous_param));
+        }
+
+        if let Some(nice) = config.nice {
+            let previous_nice = thread_nice()?;
+            // Lowering the nice value again needs privileges, without them the thread would stay
+            // niced for every later execution on it.
+            if nice > previous_nice && !can_lower_nice_to(previous_nice)? {
+                bail!(
+                    "Nice value {nice} couldn't be reverted to {previous_nice}, which needs \
+                     CAP_SYS_NICE or an RLIMIT_NICE of at least {}",
+                    20 - previous_nice
+                );
+            }
+
+            // SAFETY: `setpriority` has no memory safety preconditions.
+            check(unsafe { libc::setpriority(libc::PRIO_PROCESS, thread_id(), nice) })?;
+            guard.previous_nice = Some(previous_nice);
+        }
+
+        Ok(guard)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for SchedulingGuard {
+    fn drop(&mut self) {
+        // `apply` only changes what the thread is permitted to change back, so the errors ignored
+        // here are limited to e.g. CPUs that were taken away from the process in the meantime.
+        // SAFETY: the same calling thread and valid pointers as in `SchedulingGuard::apply`.
+        unsafe {
+            if let Some(nice) = self.previous_nice {
+                libc::setpriority(libc::PRIO_PROCESS, thread_id(), nice);
+            }
+            if let Some((policy, param)) = self.previous_policy {
+                libc::sched_setscheduler(0, policy, &param);
+            }
+            if let Some(affinity) = &self.previous_affinity {
+                libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), affinity);
+            }
+        }
+    }
+}
+
+/// On Linux `PRIO_PROCESS` with a thread id only affects that thread.
+#[cfg(target_os = "linux")]
+fn thread_id() -> libc::id_t {
+    // SAFETY: `gettid` has no preconditions.
+    unsafe { libc::gettid() as libc::id_t }
+}
+
+#[cfg(target_os = "linux")]
+fn thread_nice() -> io::Result<libc::c_int> {
+    // `getpriority` can legitimately return -1, so errno has to be cleared and checked.
+    // SAFETY: `__errno_location` always returns a valid pointer for the calling thread.
+    unsafe { *libc::__errno_location() = 0 };
+    let nice = unsafe { libc::getpriority(libc::PRIO_PROCESS, thread_id()) };
+    if nice == -1 && io::Error::last_os_error().raw_os_error() != Some(0) {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(nice)
+}
+
+/// Whether the calling thread may lower its nice value to `nice`, which needs `CAP_SYS_NICE` or an
+/// `RLIMIT_NICE` of at least `20 - nice`.
+#[cfg(target_os = "linux")]
+fn can_lower_nice_to(nice: libc::c_int) -> io::Result<bool> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: `limit` is a valid pointer.
+    check(unsafe { libc::getrlimit(libc::RLIMIT_NICE, &mut limit) })?;
+    if (20 - nice) as libc::rlim_t <= limit.rlim_cur {
+        return Ok(true);
+    }
+    Ok(has_effective_capability(CAP_SYS_NICE))
+}
+
+#[cfg(target_os = "linux")]
+const CAP_SYS_NICE: u32 = 23;
+
+#[cfg(target_os = "linux")]
+fn has_effective_capability(capability: u32) -> bool {
+    let Ok(status) = std::fs::read_to_string("/proc/thread-self/status") else {
+        return false;
+    };
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|capabilities| u64::from_str_radix(capabilities.trim(), 16).ok())
+        .is_some_and(|capabilities| capabilities & (1 << capability) != 0)
+}
+
+/// Pins the calling thread to `cpus` for the rest of its lifetime, e.g. from a thread start hook
+/// of a thread pool.
+#[cfg(target_os = "linux")]
+pub fn set_thread_affinity(cpus: &[usize]) -> Result<()> {
+    let cpu_set = cpu_set(cpus)?;
+    // SAFETY: pid 0 refers to the calling thread and the pointer is valid for the size.
+    check(unsafe { libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &cpu_set) })?;
+    Ok(())
+}
+
+/// Fails unless every CPU of `cpus` is in the affinity mask of the calling thread, which threads
+/// spawned from it inherit.
+#[cfg(target_os = "linux")]
+pub fn check_cpus_allowed(cpus: &[usize]) -> Result<()> {
+    cpu_set(cpus)?;
+    // SAFETY: `cpu_set_t` is a plain bit set for which all zeroes is a valid value.
+    let mut allowed: libc::cpu_set_t = unsafe { mem::zeroed() };
+    // SAFETY: pid 0 refers to the calling thread and the pointer is valid for the size.
+    check(unsafe { libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut allowed) })?;
+    // SAFETY: `cpu_set` checked that the CPUs are within the set's bounds.
+    let disallowed: Vec<_> = cpus
+        .iter()
+        .filter(|&&cpu| !unsafe { libc::CPU_ISSET(cpu, &allowed) })
+        .map(usize::to_string)
+        .collect();
+    if !disallowed.is_empty() {
+        bail!(
+            "CPUs {} are not in the affinity mask of the process",
+            disallowed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn cpu_set(cpus: &[usize]) -> Result<libc::cpu_set_t> {
+    if cpus.is_empty() {
+        bail!("CPU affinity must contain at least one CPU");
+    }
+
+    // SAFETY: `cpu_set_t` is a plain bit set for which all zeroes is a valid value.
+    let mut cpu_set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    for &cpu in cpus {
+        if cpu >= libc::CPU_SETSIZE as usize {
+            bail!("CPU {cpu} exceeds the maximum of {}", libc::CPU_SETSIZE);
+        }
+        // SAFETY: `cpu` was checked to be within the set's bounds.
+        unsafe { libc::CPU_SET(cpu, &mut cpu_set) };
+    }
+    Ok(cpu_set)
+}
+
+/// The CPU the calling thread is currently running on, if it can be determined.
+#[cfg(target_os = "linux")]
+pub(crate) fn current_cpu() -> Option<u32> {
+    // SAFETY: `sched_getcpu` has no preconditions.
+    let cpu = unsafe { libc::sched_getcpu() };
+    u32::try_from(cpu).ok()
+}
+
+#[cfg(target_os = "linux")]
+fn check(ret: libc::c_int) -> io::Result<()> {
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Stand-in for other targets, which only accepts a config that doesn't change anything.
+#[cfg(not(target_os = "linux"))]
+pub(crate) struct SchedulingGuard;
+
+#[cfg(not(target_os = "linux"))]
+impl SchedulingGuard {
+    pub(crate) fn apply(config: &SchedulingConfig) -> Result<Self> {
+        let SchedulingConfig {
+            cpu_affinity,
+            fifo_priority,
+            nice,
+        } = config;
+        if cpu_affinity.is_some() || fifo_priority.is_some() || nice.is_some() {
+            bail!("Thread scheduling is only supported on Linux");
+        }
+        Ok(Self)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_thread_affinity(_cpus: &[usize]) -> Result<()> {
+    bail!("Thread affinity is only supported on Linux")
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn check_cpus_allowed(_cpus: &[usize]) -> Result<()> {
+    bail!("Thread affinity is only supported on Linux")
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn current_cpu() -> Option<u32> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn allowed_cpu_count() -> Result<libc::c_int> {
+        let mut affinity: libc::cpu_set_t = unsafe { mem::zeroed() };
+        check(unsafe {
+            libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut affinity)
+        })?;
+        Ok(unsafe { libc::CPU_COUNT(&affinity) })
+    }
+
+    #[test]
+    fn test_cpu_affinity_is_applied_and_restored() -> Result<()> {
+        let cpu = current_cpu().expect("sched_getcpu is supported");
+        let allowed_cpus = allowed_cpu_count()?;
+
+        {
+            let _guard = SchedulingGuard::apply(&SchedulingConfig {
+                cpu_affinity: Some(vec![cpu as usize]),
+                ..SchedulingConfig::default()
+            })?;
+            assert_eq!(allowed_cpu_count()?, 1);
+            assert_eq!(current_cpu(), Some(cpu));
+        }
+
+        assert_eq!(allowed_cpu_count()?, allowed_cpus);
+        Ok(())
+    }
+
+    #[test]
+    fn test_nice_is_restored_or_rejected() -> Result<()> {
+        thread::spawn(|| {
+            let previous_nice = thread_nice()?;
+            let nice = (previous_nice + 1).min(19);
+            match SchedulingGuard::apply(&SchedulingConfig {
+                nice: Some(nice),
+                ..SchedulingConfig::default()
+            }) {
+                Ok(guard) => {
+                    assert_eq!(thread_nice()?, nice);
+                    drop(guard);
+                }
+                Err(err) => assert!(!can_lower_nice_to(previous_nice)?, "{err}"),
+            }
+            assert_eq!(thread_nice()?, previous_nice);
+            Ok(())
+        })
+        .join()
+        .expect("nice thread panicked")
+    }
+
+    #[test]
+    fn test_set_thread_affinity() -> Result<()> {
+        let cpu = current_cpu().expect("sched_getcpu is supported") as usize;
+        thread::spawn(move || {
+            set_thread_affinity(&[cpu])?;
+            assert_eq!(allowed_cpu_count()?, 1);
+            assert!(set_thread_affinity(&[]).is_err());
+
+            check_cpus_allowed(&[cpu])?;
+            let other_cpu = (cpu + 1) % libc::CPU_SETSIZE as usize;
+            assert!(check_cpus_allowed(&[cpu, other_cpu]).is_err());
+            Ok(())
+        })
+        .join()
+        .expect("affinity thread panicked")
+    }
+}
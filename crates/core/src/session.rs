@@ -0,0 +1,467 @@
+use crate::{
+    decode_creation_code, load_test_artifact, Artifact, ArtifactCode, TestArtifact, TestContext,
+    TestError,
+};
+use eyre::{bail, eyre, Result};
+use revm::{
+    bytecode::Bytecode,
+    context::{
+        result::{ExecutionResult, Output},
+        Context, ContextTr, TxEnv,
+    },
+    database::{DatabaseRef, InMemoryDB},
+    handler::{ExecuteCommitEvm, ExecuteEvm, MainBuilder, MainContext},
+    primitives::{Address, Bytes, Log, TxKind, B256, U256},
+    state::AccountInfo,
+    MainnetEvm,
+};
+use serde::Serialize;
+use std::{fmt, time::Instant};
+
+/// Gas limit of transactions that don't set one, same as [`crate::execute_test`].
+pub const DEFAULT_GAS_LIMIT: u64 = 30_000_000;
+
+/// Selector of `Error(string)`, which `revert("reason")` and `require(false, "reason")` return.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Selector of `Panic(uint256)`, which failed assertions and arithmetic errors return.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// A long-lived REVM instance with an in-memory database that transactions are committed to, for
+/// driving REVM incrementally instead of building a throwaway EVM per test.
+pub struct EvmSession {
+    evm: MainnetEvm<TestContext>,
+    /// Copies of the database, indexed by snapshot id.
+    snapshots: Vec<InMemoryDB>,
+}
+
+/// A transaction to call or create a contract.
+#[derive(Clone, Debug)]
+pub struct CallRequest {
+    pub caller: Address,
+    /// Contract to call, or `None` to create a contract from `data`.
+    pub to: Option<Address>,
+    pub data: Bytes,
+    pub value: U256,
+    pub gas_limit: u64,
+}
+
+impl Default for CallRequest {
+    fn default() -> Self {
+        Self {
+            caller: Address::ZERO,
+            to: None,
+            data: Bytes::new(),
+            value: U256::ZERO,
+            gas_limit: DEFAULT_GAS_LIMIT,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CallStatus {
+    Success,
+    /// Reverted by the `REVERT` opcode.
+    Revert,
+    /// Stopped by an exceptional halt, such as running out of gas, which consumes all gas.
+    Halt,
+}
+
+/// Result of a call or transaction.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallOutcome {
+    pub status: CallStatus,
+    pub gas_used: u64,
+    /// Return or revert data.
+    pub output: Bytes,
+    /// Message of an `Error(string)` or `Panic(uint256)` revert.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub halt_reason: Option<String>,
+    pub logs: Vec<Log>,
+    /// Address of the contract created by a create transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_address: Option<Address>,
+    /// Execution time of the REVM transaction.
+    pub duration_ns: f64,
+}
+
+/// Balance, nonce and code of an account.
+#[derive(Clone, Debug, Default)]
+pub struct AccountState {
+    pub balance: U256,
+    pub nonce: u64,
+    pub code: Bytes,
+}
+
+impl Default for EvmSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EvmSession {
+    pub fn new() -> Self {
+        let ctx: TestContext = Context::mainnet().with_db(InMemoryDB::default());
+        Self {
+            evm: ctx.build_mainnet(),
+            snapshots: Vec::new(),
+        }
+    }
+
+    /// Runs the creation code of a Solidity artifact followed by `constructor_args` and commits
+    /// the created contract. Raw bytecode is deployed as is, without running a constructor.
+    pub fn deploy_artifact(
+        &mut self,
+        artifact: &TestArtifact,
+        caller: Address,
+        constructor_args: &[u8],
+    ) -> Result<CallOutcome> {
+        let mut data = match load_test_artifact(artifact)? {
+            Artifact {
+                bytecode: None,
+                deployed_bytecode: ArtifactCode::Raw(code),
+                ..
+            } => wrap_in_creation_code(&code)?,
+            artifact => decode_creation_code(&artifact)?,
+        };
+        data.extend_from_slice(constructor_args);
+
+        let outcome = self.transact(CallRequest {
+            caller,
+            data: data.into(),
+            ..CallRequest::default()
+        })?;
+        let failure = match outcome.status {
+            CallStatus::Success => return Ok(outcome),
+            CallStatus::Revert => TestError::TestReverted {
+                reason: outcome.revert_reason.clone(),
+                output: outcome.output.clone(),
+                gas_used: outcome.gas_used,
+            },
+            CallStatus::Halt => TestError::TestHalted {
+                reason: outcome.failure_reason(),
+                gas_used: outcome.gas_used,
+            },
+        };
+        let context = match artifact {
+            TestArtifact::Path(path) => format!("Deploying {} failed", path.display()),
+            _ => "Deploying artifact failed".to_string(),
+        };
+        Err(eyre::Report::new(failure).wrap_err(format!("{context}: {}", outcome.failure_reason())))
+    }
+
+    /// Replaces the balance, nonce and code of an account. Its storage is kept.
+    pub fn set_account(&mut self, address: Address, account: AccountState) -> Result<()> {
+        let code = Bytecode::new_raw_checked(account.code)?;
+        self.db_mut().insert_account_info(
+            address,
+            AccountInfo {
+                balance: account.balance,
+                nonce: account.nonce,
+                code_hash: code.hash_slow(),
+                code: Some(code),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn set_storage(&mut self, address: Address, slot: U256, value: U256) -> Result<()> {
+        self.db_mut().insert_account_storage(address, slot, value)?;
+        Ok(())
+    }
+
+    /// The account at `address`, or `None` if it doesn't exist.
+    pub fn account(&self, address: Address) -> Result<Option<AccountState>> {
+        let db = self.evm.ctx.db();
+        let Some(info) = db.basic_ref(address)? else {
+            return Ok(None);
+        };
+        let code = match info.code {
+            Some(code) => code,
+            None => db.code_by_hash_ref(info.code_hash)?,
+        };
+        Ok(Some(AccountState {
+            balance: info.balance,
+            nonce: info.nonce,
+            code: code.original_bytes(),
+        }))
+    }
+
+    pub fn storage(&self, address: Address, slot: U256) -> Result<U256> {
+        Ok(self.evm.ctx.db().storage_ref(address, slot)?)
+    }
+
+    /// Executes a transaction without committing its state changes.
+    pub fn call(&mut self, request: CallRequest) -> Result<CallOutcome> {
+        let tx = self.build_tx(request)?;
+        let start = Instant::now();
+        let result = self.evm.transact(tx).map_err(evm_failed)?;
+        let elapsed = start.elapsed();
+        Ok(CallOutcome::new(result.result, elapsed.as_nanos() as f64))
+    }
+
+    /// Executes a transaction and commits its state changes, including those of a failed
+    /// transaction such as the caller's nonce.
+    pub fn transact(&mut self, request: CallRequest) -> Result<CallOutcome> {
+        let tx = self.build_tx(request)?;
+        let start = Instant::now();
+        let result = self.evm.transact_commit(tx).map_err(evm_failed)?;
+        let elapsed = start.elapsed();
+        Ok(CallOutcome::new(result, elapsed.as_nanos() as f64))
+    }
+
+    /// Saves the current state and returns an id to [`revert`](Self::revert) to.
+    pub fn snapshot(&mut self) -> usize {
+        let db = self.evm.ctx.db().clone();
+        self.snapshots.push(db);
+        self.snapshots.len() - 1
+    }
+
+    /// Restores the state saved by [`snapshot`](Self::snapshot). The snapshot and all snapshots
+    /// taken after it are discarded.
+    pub fn revert(&mut self, snapshot_id: usize) -> Result<()> {
+        if snapshot_id >= self.snapshots.len() {
+            bail!("Snapshot {snapshot_id} doesn't exist");
+        }
+        self.snapshots.truncate(snapshot_id + 1);
+        let db = self.snapshots.pop().expect("snapshot exists");
+        *self.db_mut() = db;
+        Ok(())
+    }
+
+    fn db_mut(&mut self) -> &mut InMemoryDB {
+        self.evm.ctx.db_mut()
+    }
+
+    fn build_tx(&self, request: CallRequest) -> Result<TxEnv> {
+        let CallRequest {
+            caller,
+            to,
+            data,
+            value,
+            gas_limit,
+        } = request;
+        let nonce = self
+            .evm
+            .ctx
+            .db()
+            .basic_ref(caller)?
+            .map(|info| info.nonce)
+            .unwrap_or_default();
+        TxEnv::builder()
+            .caller(caller)
+            .kind(to.map_or(TxKind::Create, TxKind::Call))
+            .data(data)
+            .value(value)
+            .gas_limit(gas_limit)
+            .nonce(nonce)
+            .build()
+            .map_err(|err| eyre!("{:?}", err))
+    }
+}
+
+impl CallOutcome {
+    fn new(result: ExecutionResult, duration_ns: f64) -> Self {
+        match result {
+            ExecutionResult::Success {
+                gas_used,
+                logs,
+                output,
+                ..
+            } => {
+                let created_address = match &output {
+                    Output::Create(_, address) => *address,
+                    Output::Call(_) => None,
+                };
+                Self {
+                    status: CallStatus::Success,
+                    gas_used,
+                    output: output.into_data(),
+                    revert_reason: None,
+                    halt_reason: None,
+                    logs,
+                    created_address,
+                    duration_ns,
+                }
+            }
+            ExecutionResult::Revert { gas_used, output } => Self {
+                status: CallStatus::Revert,
+                gas_used,
+                revert_reason: decode_revert_reason(&output),
+                output,
+                halt_reason: None,
+                logs: Vec::new(),
+                created_address: None,
+                duration_ns,
+            },
+            ExecutionResult::Halt { reason, gas_used } => Self {
+                status: CallStatus::Halt,
+                gas_used,
+                output: Bytes::new(),
+                revert_reason: None,
+                halt_reason: Some(format!("{reason:?}")),
+                logs: Vec::new(),
+                created_address: None,
+                duration_ns,
+            },
+        }
+    }
+
+    fn failure_reason(&self) -> String {
+        self.revert_reason
+            .clone()
+            .or_else(|| self.halt_reason.clone())
+            .unwrap_or_else(|| format!("{:?} without a reason", self.status))
+    }
+}
+
+/// Creation code that returns `code`, so that it becomes the code of the created contract.
+fn wrap_in_creation_code(code: &[u8]) -> Result<Vec<u8>, TestError> {
+    let length = u16::try_from(code.len()).map_err(|_| TestError::InvalidBytecode {
+        message: format!("Bytecode of {} bytes is too large to deploy", code.len()),
+    })?;
+    let [high, low] = length.to_be_bytes();
+    // PUSH2 length, DUP1, PUSH1 12, PUSH1 0, CODECOPY, PUSH1 0, RETURN
+    let mut creation_code = vec![
+        0x61, high, low, 0x80, 0x60, 0x0c, 0x60, 0x00, 0x39, 0x60, 0x00, 0xf3,
+    ];
+    creation_code.extend_from_slice(code);
+    Ok(creation_code)
+}
+
+fn evm_failed(err: impl fmt::Display) -> TestError {
+    TestError::EvmFailed {
+        message: err.to_string(),
+    }
+}
+
+/// Decodes the message of `Error(string)` and the code of `Panic(uint256)` revert data.
+pub(crate) fn decode_revert_reason(output: &[u8]) -> Option<String> {
+    let (selector, data) = output.split_first_chunk::<4>()?;
+    match *selector {
+        ERROR_SELECTOR => {
+            let offset = usize::try_from(U256::from_be_slice(data.get(..32)?)).ok()?;
+            let length_end = offset.checked_add(32)?;
+            let length =
+                usize::try_from(U256::from_be_slice(data.get(offset..length_end)?)).ok()?;
+            let message = data.get(length_end..length_end.checked_add(length)?)?;
+            Some(String::from_utf8_lossy(message).into_owned())
+        }
+        PANIC_SELECTOR => {
+            let code = B256::from_slice(data.get(..32)?);
+            Some(format!("Panic({:#x})", U256::from_be_bytes(code.0)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorCode;
+    use revm::primitives::{address, hex, keccak256};
+    use std::path::PathBuf;
+
+    const TEST_ARTIFACT: &str = "../../contracts/Avg_Unit_Test.json";
+
+    #[test]
+    fn test_session_deploy_call_snapshot_revert() -> Result<()> {
+        let artifact_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(TEST_ARTIFACT);
+        let caller = address!("0100000000000000000000000000000000000000");
+        let mut session = EvmSession::new();
+
+        let deployment =
+            session.deploy_artifact(&TestArtifact::Path(artifact_path), caller, &[])?;
+        let contract = deployment.created_address.expect("create transaction");
+        assert!(!session
+            .account(contract)?
+            .expect("deployed")
+            .code
+            .is_empty());
+        assert_eq!(session.account(caller)?.expect("sender").nonce, 1);
+
+        let snapshot = session.snapshot();
+        session.set_storage(contract, U256::from(1), U256::from(42))?;
+        assert_eq!(session.storage(contract, U256::from(1))?, U256::from(42));
+        session.revert(snapshot)?;
+        assert_eq!(session.storage(contract, U256::from(1))?, U256::ZERO);
+        assert!(session.revert(snapshot).is_err());
+
+        let selector = &keccak256("test_Avg_OneOperandEvenTheOtherOdd()")[..4];
+        let outcome = session.call(CallRequest {
+            caller,
+            to: Some(contract),
+            data: Bytes::copy_from_slice(selector),
+            ..CallRequest::default()
+        })?;
+        assert_eq!(outcome.status, CallStatus::Success);
+        assert!(outcome.gas_used > 0);
+        assert_eq!(session.account(caller)?.expect("sender").nonce, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_deploy_raw_bytecode() -> Result<()> {
+        let code = Bytes::from_static(&hex!("602a60005260206000f3"));
+        let mut session = EvmSession::new();
+
+        let deployment =
+            session.deploy_artifact(&TestArtifact::Bytecode(code.clone()), Address::ZERO, &[])?;
+        let contract = deployment.created_address.expect("create transaction");
+        assert_eq!(session.account(contract)?.expect("deployed").code, code);
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_deploy_errors_are_typed() {
+        let mut session = EvmSession::new();
+        let missing = PathBuf::from("Missing.json");
+        let err = session
+            .deploy_artifact(&TestArtifact::Path(missing), Address::ZERO, &[])
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<TestError>().map(TestError::code),
+            Some(ErrorCode::ArtifactNotFound)
+        );
+
+        // PUSH1 0, PUSH1 0, REVERT
+        let reverting = TestArtifact::Json(serde_json::json!({
+            "bytecode": { "object": "0x60006000fd" },
+            "deployedBytecode": { "object": "0x" },
+        }));
+        let err = session
+            .deploy_artifact(&reverting, Address::ZERO, &[])
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<TestError>().map(TestError::code),
+            Some(ErrorCode::TestReverted)
+        );
+        assert_eq!(
+            err.to_string(),
+            "Deploying artifact failed: Revert without a reason"
+        );
+    }
+
+    #[test]
+    fn test_decode_revert_reason() {
+        let error = hex!(
+            "08c379a0"
+            "0000000000000000000000000000000000000000000000000000000000000020"
+            "000000000000000000000000000000000000000000000000000000000000000b"
+            "5344353978313820617667000000000000000000000000000000000000000000"
+        );
+        assert_eq!(decode_revert_reason(&error).as_deref(), Some("SD59x18 avg"));
+
+        let panic = hex!(
+            "4e487b71"
+            "0000000000000000000000000000000000000000000000000000000000000011"
+        );
+        assert_eq!(decode_revert_reason(&panic).as_deref(), Some("Panic(0x11)"));
+
+        assert_eq!(decode_revert_reason(&[]), None);
+    }
+}
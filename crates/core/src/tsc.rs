@@ -0,0 +1,144 @@
+use eyre::{eyre, Result};
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// Duration of the REVM transaction measured with the CPU's timestamp counter.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TscTiming {
+    /// Timestamp counter ticks: TSC cycles on x86_64, generic timer ticks on aarch64.
+    pub cycles: u64,
+    /// `cycles` converted to nanoseconds.
+    pub duration_ns: f64,
+    /// Calibrated counter frequency.
+    pub frequency_hz: f64,
+}
+
+/// How long the TSC is compared against `Instant` to calibrate its frequency on x86_64.
+#[cfg(target_arch = "x86_64")]
+const CALIBRATION_DURATION: std::time::Duration = std::time::Duration::from_millis(20);
+
+/// Frequency of the timestamp counter, calibrated once per process.
+pub(crate) fn frequency_hz() -> Result<f64> {
+    static FREQUENCY: OnceLock<Result<f64, String>> = OnceLock::new();
+    FREQUENCY
+        .get_or_init(calibrate)
+        .clone()
+        .map_err(|err| eyre!(err))
+}
+
+pub(crate) fn timing(start: u64, end: u64, frequency_hz: f64) -> TscTiming {
+    let cycles = end.saturating_sub(start);
+    TscTiming {
+        cycles,
+        duration_ns: cycles as f64 * 1e9 / frequency_hz,
+        frequency_hz,
+    }
+}
+
+/// Reads the counter at the start of a measured region. The fences keep earlier instructions
+/// from finishing inside the region and later ones from starting before the read.
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+pub(crate) fn start() -> u64 {
+    use std::arch::x86_64::{_mm_lfence, _rdtsc};
+    // SAFETY: `lfence` and `rdtsc` are available on every x86_64 CPU.
+    unsafe {
+        _mm_lfence();
+        let tsc = _rdtsc();
+        _mm_lfence();
+        tsc
+    }
+}
+
+/// Reads the counter at the end of a measured region. `rdtscp` waits for all earlier instructions
+/// to complete and the fence keeps later ones from starting before the read.
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+pub(crate) fn end() -> u64 {
+    use std::arch::x86_64::{__rdtscp, _mm_lfence};
+    let mut aux = 0;
+    // SAFETY: `rdtscp` is available on every x86_64 CPU since 2008 and `aux` is a valid pointer.
+    unsafe {
+        let tsc = __rdtscp(&mut aux);
+        _mm_lfence();
+        tsc
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn calibrate() -> Result<f64, String> {
+    let instant_start = std::time::Instant::now();
+    let tsc_start = start();
+    while instant_start.elapsed() < CALIBRATION_DURATION {
+        std::hint::spin_loop();
+    }
+    let tsc_end = end();
+    let elapsed = instant_start.elapsed();
+
+    Ok(tsc_end.saturating_sub(tsc_start) as f64 / elapsed.as_secs_f64())
+}
+
+/// Reads the virtual counter, with an instruction barrier so that it isn't read early.
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+pub(crate) fn start() -> u64 {
+    let counter: u64;
+    // SAFETY: `cntvct_el0` is readable from EL0 on Linux.
+    unsafe { std::arch::asm!("isb", "mrs {}, cntvct_el0", out(reg) counter, options(nostack)) };
+    counter
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+pub(crate) fn end() -> u64 {
+    start()
+}
+
+/// The generic timer reports its own frequency, so no calibration is needed.
+#[cfg(target_arch = "aarch64")]
+fn calibrate() -> Result<f64, String> {
+    let frequency: u64;
+    // SAFETY: `cntfrq_el0` is readable from EL0 on Linux.
+    unsafe { std::arch::asm!("mrs {}, cntfrq_el0", out(reg) frequency, options(nomem, nostack)) };
+    Ok(frequency as f64)
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub(crate) fn start() -> u64 {
+    0
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub(crate) fn end() -> u64 {
+    0
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn calibrate() -> Result<f64, String> {
+    Err("Timestamp counter timing is only supported on x86_64 and aarch64".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_tsc_timing_matches_instant() -> Result<()> {
+        let frequency_hz = frequency_hz()?;
+        assert!(frequency_hz > 0.0);
+
+        let instant_start = Instant::now();
+        let tsc_start = start();
+        std::thread::sleep(Duration::from_millis(10));
+        let tsc_end = end();
+        let elapsed = instant_start.elapsed();
+
+        let timing = timing(tsc_start, tsc_end, frequency_hz);
+        assert!(timing.cycles > 0);
+        let ratio = timing.duration_ns / elapsed.as_nanos() as f64;
+        assert!((0.9..=1.1).contains(&ratio), "TSC/Instant ratio {ratio}");
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{
+    execute_test_with_options, scheduling::SchedulingGuard, ExecuteTestOptions, SchedulingConfig,
+    TestResult, ThreadKind,
+};
+use eyre::{bail, eyre, Result};
+use std::{
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+};
+
+/// A test to execute, identified by its artifact and function signature.
+#[derive(Clone, Debug)]
+pub struct TestCase {
+    pub test_artifact_path: PathBuf,
+    pub test_name: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct WorkerPoolConfig {
+    /// Number of worker threads. Defaults to the available parallelism.
+    pub size: Option<usize>,
+    /// Stack size in bytes of the worker threads. Uses the Rust default if not set.
+    pub stack_size: Option<usize>,
+    /// CPUs to pin the workers to, worker `i` is pinned to `cpu_affinity[i % cpu_affinity.len()]`.
+    pub cpu_affinity: Option<Vec<usize>>,
+    /// Test that every worker executes before taking jobs, to warm up its caches and allocator.
+    pub warm_up: Option<WarmUpConfig>,
+}
+
+#[derive(Clone, Debug)]
+pub struct WarmUpConfig {
+    pub test: TestCase,
+    /// Number of times each worker executes the warm-up test.
+    pub iterations: usize,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Pool of long-lived worker threads that take tests from a shared queue, so that an idle worker
+/// picks up the next test.
+pub struct WorkerPool {
+    sender: Option<mpsc::Sender<Job>>,
+    workers: Vec<thread::JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    /// Spawns the workers and blocks until all of them are pinned and warmed up.
+    pub fn new(config: WorkerPoolConfig) -> Result<Self> {
+        let size = match config.size {
+            Some(0) => bail!("Worker pool needs at least one worker"),
+            Some(size) => size,
+            None => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        };
+        if config.cpu_affinity.as_ref().is_some_and(Vec::is_empty) {
+            bail!("CPU affinity must contain at least one CPU");
+        }
+
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<()>>();
+        let mut pool = Self {
+            sender: Some(sender),
+            workers: Vec::with_capacity(size),
+        };
+
+        for index in 0..size {
+            let receiver = receiver.clone();
+            let ready_tx = ready_tx.clone();
+            let scheduling = config.cpu_affinity.as_ref().map(|cpus| SchedulingConfig {
+                cpu_affinity: Some(vec![cpus[index % cpus.len()]]),
+                ..SchedulingConfig::default()
+            });
+            let warm_up = config.warm_up.clone();
+
+            let mut builder = thread::Builder::new().name(format!("revm-worker-{index}"));
+            if let Some(stack_size) = config.stack_size {
+                builder = builder.stack_size(stack_size);
+            }
+            let worker = builder.spawn(move || {
+                // The guard is held for the lifetime of the worker to keep it pinned.
+                let (_scheduling_guard, ready) =
+                    match scheduling.as_ref().map(SchedulingGuard::apply).transpose() {
+                        Ok(guard) => (guard, warm_up.as_ref().map_or(Ok(()), WarmUpConfig::run)),
+                        Err(err) => (None, Err(err)),
+                    };
+                let _ = ready_tx.send(ready);
+                drop(ready_tx);
+
+                // The lock is released before the job runs, so other workers can take the next job.
+                while let Ok(Ok(job)) = receiver.lock().map(|receiver| receiver.recv()) {
+                    // A panicking test must not take the worker down with it. Its result sender is
+                    // dropped, which the caller reports as an error.
+                    let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                }
+            })?;
+            pool.workers.push(worker);
+        }
+        drop(ready_tx);
+
+        for ready in ready_rx {
+            ready?;
+        }
+        Ok(pool)
+    }
+
+    /// Number of worker threads.
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Distributes the tests across the workers and blocks until all of them completed. The
+    /// results are in the order of `tests`.
+    pub fn run_many(
+        &self,
+        tests: Vec<TestCase>,
+        options: ExecuteTestOptions,
+    ) -> Result<Vec<Result<TestResult>>> {
+        let sender = self.sender.as_ref().expect("sender is only taken on drop");
+        let (result_tx, result_rx) = mpsc::channel();
+        let count = tests.len();
+        for (index, test) in tests.into_iter().enumerate() {
+            let result_tx = result_tx.clone();
+            let options = options.clone();
+            let job: Job = Box::new(move || {
+                let result = test.execute(options).map(|mut test_result| {
+                    test_result.thread_kind = Some(ThreadKind::DedicatedWorker);
+                    test_result
+                });
+                let _ = result_tx.send((index, result));
+            });
+            sender
+                .send(job)
+                .map_err(|_| eyre!("Worker pool has shut down"))?;
+        }
+        drop(result_tx);
+
+        let mut results: Vec<Option<Result<TestResult>>> = (0..count).map(|_| None).collect();
+        for (index, result) in result_rx {
+            results[index] = Some(result);
+        }
+        Ok(results
+            .into_iter()
+            .map(|result| {
+                result.unwrap_or_else(|| Err(eyre!("Worker panicked while executing the test")))
+            })
+            .collect())
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // Closing the queue makes the workers exit once they finished their current job.
+        drop(self.sender.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl TestCase {
+    fn execute(&self, options: ExecuteTestOptions) -> Result<TestResult> {
+        execute_test_with_options(&self.test_artifact_path, &self.test_name, options)
+    }
+}
+
+impl WarmUpConfig {
+    fn run(&self) -> Result<()> {
+        for _ in 0..self.iterations {
+            self.test.execute(ExecuteTestOptions::default())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_ARTIFACT: &str = "../../contracts/Avg_Unit_Test.json";
+    const TEST_NAME: &str = "test_Avg_OneOperandEvenTheOtherOdd()";
+
+    #[test]
+    fn test_worker_pool_run_many() -> Result<()> {
+        let test = TestCase {
+            test_artifact_path: PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(TEST_ARTIFACT),
+            test_name: TEST_NAME.to_string(),
+        };
+        let pool = WorkerPool::new(WorkerPoolConfig {
+            size: Some(2),
+            warm_up: Some(WarmUpConfig {
+                test: test.clone(),
+                iterations: 1,
+            }),
+            ..WorkerPoolConfig::default()
+        })?;
+        assert_eq!(pool.size(), 2);
+
+        let failing = TestCase {
+            test_name: "test_missing()".to_string(),
+            ..test.clone()
+        };
+        let results = pool.run_many(
+            vec![test.clone(), failing, test],
+            ExecuteTestOptions::default(),
+        )?;
+        assert_eq!(results.len(), 3);
+        assert!(results[1].is_err());
+        for result in [&results[0], &results[2]] {
+            let test_result = result.as_ref().expect("test passes");
+            assert_eq!(test_result.thread_kind, Some(ThreadKind::DedicatedWorker));
+            assert!(test_result
+                .thread_name
+                .as_deref()
+                .is_some_and(|name| name.starts_with("revm-worker-")));
+        }
+
+        assert!(WorkerPool::new(WorkerPoolConfig {
+            size: Some(0),
+            ..WorkerPoolConfig::default()
+        })
+        .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{IntegerFormat, Quantity};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use napi_rs_revm_core::TestError;
+
+/// Stable identifier of a failure, set as `code` of the thrown errors
+#[napi(string_enum = "UPPER_SNAKE")]
+pub enum ErrorCode {
+    ArtifactNotFound,
+    ArtifactUnreadable,
+    InvalidArtifact,
+    InvalidBytecode,
+    UnknownTest,
+    SchedulingFailed,
+    PerfEventsUnavailable,
+    InstrumentationFailed,
+    EvmFailed,
+    TestReverted,
+    TestHalted,
+    Aborted,
+    /// Any failure that isn't specific to a test, e.g. a worker that panicked
+    Internal,
+}
+
+impl From<napi_rs_revm_core::ErrorCode> for ErrorCode {
+    fn from(value: napi_rs_revm_core::ErrorCode) -> Self {
+        use napi_rs_revm_core::ErrorCode as Core;
+        match value {
+            Core::ArtifactNotFound => Self::ArtifactNotFound,
+            Core::ArtifactUnreadable => Self::ArtifactUnreadable,
+            Core::InvalidArtifact => Self::InvalidArtifact,
+            Core::InvalidBytecode => Self::InvalidBytecode,
+            Core::UnknownTest => Self::UnknownTest,
+            Core::SchedulingFailed => Self::SchedulingFailed,
+            Core::PerfEventsUnavailable => Self::PerfEventsUnavailable,
+            Core::InstrumentationFailed => Self::InstrumentationFailed,
+            Core::EvmFailed => Self::EvmFailed,
+            Core::TestReverted => Self::TestReverted,
+            Core::TestHalted => Self::TestHalted,
+            Core::Aborted => Self::Aborted,
+        }
+    }
+}
+
+/// Phase of a test execution that a failure occurred in
+#[napi(string_enum = "kebab-case")]
+pub enum ErrorPhase {
+    Load,
+    Decode,
+    Analyse,
+    Build,
+    /// Scheduling and the instrumentation around the REVM transaction
+    Setup,
+    Transact,
+    ResultHandling,
+}
+
+impl From<napi_rs_revm_core::ErrorPhase> for ErrorPhase {
+    fn from(value: napi_rs_revm_core::ErrorPhase) -> Self {
+        use napi_rs_revm_core::ErrorPhase as Core;
+        match value {
+            Core::Load => Self::Load,
+            Core::Decode => Self::Decode,
+            Core::Analyse => Self::Analyse,
+            Core::Build => Self::Build,
+            Core::Setup => Self::Setup,
+            Core::Transact => Self::Transact,
+            Core::ResultHandling => Self::ResultHandling,
+        }
+    }
+}
+
+/// Why a test failed. Errors thrown by test executions carry the same fields as properties
+#[napi(object)]
+pub struct TestFailure {
+    pub code: ErrorCode,
+    pub phase: Option<ErrorPhase>,
+    pub message: String,
+    /// Artifact that couldn't be loaded
+    pub path: Option<String>,
+    /// Test function that the artifact doesn't have
+    pub test_name: Option<String>,
+    /// Decoded `Error(string)` or `Panic(uint256)` of a reverted test
+    pub revert_reason: Option<String>,
+    /// Hex encoded output of a reverted test
+    pub output: Option<String>,
+    /// Gas used by a reverted or halted test
+    pub gas_used: Option<Quantity>,
+    /// Exceptional halt of a halted test, e.g. `OutOfGas(Basic)`
+    pub halt_reason: Option<String>,
+}
+
+impl TestFailure {
+    pub(crate) fn new(value: &eyre::Report, format: IntegerFormat) -> Self {
+        let mut failure = Self {
+            code: ErrorCode::Internal,
+            phase: None,
+            message: value.to_string(),
+            path: None,
+            test_name: None,
+            revert_reason: None,
+            output: None,
+            gas_used: None,
+            halt_reason: None,
+        };
+        let Some(err) = value.downcast_ref::<TestError>() else {
+            return failure;
+        };
+        failure.code = err.code().into();
+        failure.phase = Some(err.phase().into());
+        match err {
+            TestError::ArtifactNotFound { path }
+            | TestError::ArtifactUnreadable { path, .. }
+            | TestError::InvalidArtifact {
+                path: Some(path), ..
+            } => {
+                failure.path = Some(path.to_string_lossy().into_owned());
+            }
+            TestError::UnknownTest { test_name } => failure.test_name = Some(test_name.clone()),
+            TestError::TestReverted {
+                reason,
+                output,
+                gas_used,
+            } => {
+                failure.revert_reason = reason.clone();
+                failure.output = Some(output.to_string());
+                failure.gas_used = Some(format.u64(*gas_used));
+            }
+            TestError::TestHalted { reason, gas_used } => {
+                failure.halt_reason = Some(reason.clone());
+                failure.gas_used = Some(format.u64(*gas_used));
+            }
+            _ => {}
+        }
+        failure
+    }
+
+    /// JS `Error` with the message of the failure and its other fields as properties
+    pub(crate) fn into_error(self, env: &Env) -> Error {
+        let message = self.message.clone();
+        self.create_error(env)
+            .unwrap_or_else(|_| Error::from_reason(message))
+    }
+
+    fn create_error(self, env: &Env) -> Result<Error> {
+        let TestFailure {
+            code,
+            phase,
+            message,
+            path,
+            test_name,
+            revert_reason,
+            output,
+            gas_used,
+            halt_reason,
+        } = self;
+        let mut error = env.create_error(Error::from_reason(message))?;
+        error.set("code", code)?;
+        if let Some(phase) = phase {
+            error.set("phase", phase)?;
+        }
+        let details = [
+            ("path", path),
+            ("testName", test_name),
+            ("revertReason", revert_reason),
+            ("output", output),
+            ("haltReason", halt_reason),
+        ];
+        for (key, value) in details {
+            if let Some(value) = value {
+                error.set(key, value)?;
+            }
+        }
+        if let Some(gas_used) = gas_used {
+            error.set("gasUsed", gas_used)?;
+        }
+        Ok(Error::from(error.to_unknown()))
+    }
+}
+
+/// Converts the error of a test execution into a JS `Error` with the details of the failure
+pub(crate) fn test_error(env: &Env, err: eyre::Report, format: IntegerFormat) -> Error {
+    TestFailure::new(&err, format).into_error(env)
+}
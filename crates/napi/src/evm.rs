@@ -0,0 +1,360 @@
+use crate::{
+    core_artifact,
+    integer::{parse_u256, parse_u64},
+    test_error, ArtifactInput, IntegerFormat, Quantity,
+};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use napi_rs_revm_core::{Address, Bytes, EvmSession, TestArtifact};
+use std::str::FromStr;
+
+/// Long-lived REVM instance with an in-memory database that transactions are committed to
+#[napi]
+pub struct Evm {
+    session: EvmSession,
+    format: IntegerFormat,
+}
+
+#[napi]
+impl Evm {
+    #[napi(constructor)]
+    pub fn new(options: Option<EvmOptions>) -> Self {
+        let EvmOptions { integer_format } = options.unwrap_or_default();
+        Self {
+            session: EvmSession::new(),
+            format: integer_format.unwrap_or_default(),
+        }
+    }
+
+    /// Runs the creation code of a Solidity artifact and commits the created contract, whose
+    /// address is `createdAddress` of the outcome
+    #[napi]
+    pub fn deploy_artifact(
+        &mut self,
+        env: &Env,
+        artifact_path: String,
+        options: Option<DeployOptions>,
+    ) -> Result<CallOutcome> {
+        self.deploy(env, TestArtifact::Path(artifact_path.into()), options)
+    }
+
+    /// Like `deploy_artifact`, but takes the artifact from memory instead of a file. A `Buffer` of
+    /// deployed bytecode is deployed as is, without a constructor
+    #[napi(
+        ts_args_type = "artifact: Buffer | string | object, options?: DeployOptions | undefined | null"
+    )]
+    pub fn deploy_artifact_from_memory<'env>(
+        &mut self,
+        env: &'env Env,
+        artifact: ArtifactInput<'env>,
+        options: Option<DeployOptions>,
+    ) -> Result<CallOutcome> {
+        let artifact = core_artifact(env, artifact, self.format)?;
+        self.deploy(env, artifact, options)
+    }
+
+    /// Replaces the balance, nonce and code of an account. Its storage is kept
+    #[napi]
+    pub fn set_account(&mut self, address: String, account: AccountInput) -> Result<()> {
+        let AccountInput {
+            balance,
+            nonce,
+            code,
+        } = account;
+        let account = napi_rs_revm_core::AccountState {
+            balance: balance
+                .as_ref()
+                .map(parse_u256)
+                .transpose()?
+                .unwrap_or_default(),
+            nonce: nonce
+                .as_ref()
+                .map(parse_u64)
+                .transpose()?
+                .unwrap_or_default(),
+            code: code
+                .as_deref()
+                .map(parse_bytes)
+                .transpose()?
+                .unwrap_or_default(),
+        };
+        self.session
+            .set_account(parse_address(&address)?, account)
+            .map_err(|err| Error::from_reason(err.to_string()))
+    }
+
+    /// Sets a storage slot
+    #[napi]
+    pub fn set_storage(&mut self, address: String, slot: Quantity, value: Quantity) -> Result<()> {
+        self.session
+            .set_storage(
+                parse_address(&address)?,
+                parse_u256(&slot)?,
+                parse_u256(&value)?,
+            )
+            .map_err(|err| Error::from_reason(err.to_string()))
+    }
+
+    /// The account at `address`, or `null` if it doesn't exist
+    #[napi]
+    pub fn get_account(&self, address: String) -> Result<Option<AccountState>> {
+        self.session
+            .account(parse_address(&address)?)
+            .map(|account| account.map(|account| AccountState::new(account, self.format)))
+            .map_err(|err| Error::from_reason(err.to_string()))
+    }
+
+    /// Value of a storage slot
+    #[napi]
+    pub fn get_storage(&self, address: String, slot: Quantity) -> Result<Quantity> {
+        self.session
+            .storage(parse_address(&address)?, parse_u256(&slot)?)
+            .map(|value| self.format.u256(value))
+            .map_err(|err| Error::from_reason(err.to_string()))
+    }
+
+    /// Executes a transaction without committing its state changes
+    #[napi]
+    pub fn call(&mut self, env: &Env, request: CallRequest) -> Result<CallOutcome> {
+        self.session
+            .call(request.try_into()?)
+            .map(|outcome| CallOutcome::new(outcome, self.format))
+            .map_err(|err| test_error(env, err, self.format))
+    }
+
+    /// Executes a transaction and commits its state changes
+    #[napi]
+    pub fn transact(&mut self, env: &Env, request: CallRequest) -> Result<CallOutcome> {
+        self.session
+            .transact(request.try_into()?)
+            .map(|outcome| CallOutcome::new(outcome, self.format))
+            .map_err(|err| test_error(env, err, self.format))
+    }
+
+    /// Saves the current state and returns an id to revert to
+    #[napi]
+    pub fn snapshot(&mut self) -> u32 {
+        self.session.snapshot() as u32
+    }
+
+    /// Restores the state saved by `snapshot`. The snapshot and all snapshots taken after it are
+    /// discarded
+    #[napi]
+    pub fn revert(&mut self, snapshot_id: u32) -> Result<()> {
+        self.session
+            .revert(snapshot_id as usize)
+            .map_err(|err| Error::from_reason(err.to_string()))
+    }
+}
+
+impl Evm {
+    fn deploy(
+        &mut self,
+        env: &Env,
+        artifact: TestArtifact,
+        options: Option<DeployOptions>,
+    ) -> Result<CallOutcome> {
+        let DeployOptions {
+            caller,
+            constructor_args,
+        } = options.unwrap_or_default();
+        let caller = caller
+            .as_deref()
+            .map(parse_address)
+            .transpose()?
+            .unwrap_or_default();
+        let constructor_args = constructor_args
+            .as_deref()
+            .map(parse_bytes)
+            .transpose()?
+            .unwrap_or_default();
+        self.session
+            .deploy_artifact(&artifact, caller, &constructor_args)
+            .map(|outcome| CallOutcome::new(outcome, self.format))
+            .map_err(|err| test_error(env, err, self.format))
+    }
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct EvmOptions {
+    /// Format of balances, nonces, storage values and gas, defaults to `bigint`
+    pub integer_format: Option<IntegerFormat>,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct DeployOptions {
+    /// Defaults to the zero address
+    pub caller: Option<String>,
+    /// ABI-encoded constructor arguments as a hex string
+    pub constructor_args: Option<String>,
+}
+
+/// Account to set. Missing fields are zero or empty
+#[napi(object)]
+pub struct AccountInput {
+    pub balance: Option<Quantity>,
+    pub nonce: Option<Quantity>,
+    /// Deployed bytecode as a hex string
+    pub code: Option<String>,
+}
+
+#[napi(object)]
+pub struct AccountState {
+    pub balance: Quantity,
+    pub nonce: Quantity,
+    /// Deployed bytecode as a `0x`-prefixed hex string
+    pub code: String,
+}
+
+impl AccountState {
+    fn new(value: napi_rs_revm_core::AccountState, format: IntegerFormat) -> Self {
+        let napi_rs_revm_core::AccountState {
+            balance,
+            nonce,
+            code,
+        } = value;
+        Self {
+            balance: format.u256(balance),
+            nonce: format.u64(nonce),
+            code: code.to_string(),
+        }
+    }
+}
+
+/// A transaction to call or create a contract
+#[napi(object)]
+pub struct CallRequest {
+    /// Defaults to the zero address
+    pub caller: Option<String>,
+    /// Contract to call, or missing to create a contract from `data`
+    pub to: Option<String>,
+    /// Calldata or creation code as a hex string
+    pub data: Option<String>,
+    /// Wei
+    pub value: Option<Quantity>,
+    /// Defaults to 30 million
+    pub gas_limit: Option<Quantity>,
+}
+
+impl TryFrom<CallRequest> for napi_rs_revm_core::CallRequest {
+    type Error = Error;
+
+    fn try_from(value: CallRequest) -> Result<Self> {
+        let CallRequest {
+            caller,
+            to,
+            data,
+            value,
+            gas_limit,
+        } = value;
+        Ok(Self {
+            caller: caller
+                .as_deref()
+                .map(parse_address)
+                .transpose()?
+                .unwrap_or_default(),
+            to: to.as_deref().map(parse_address).transpose()?,
+            data: data
+                .as_deref()
+                .map(parse_bytes)
+                .transpose()?
+                .unwrap_or_default(),
+            value: value
+                .as_ref()
+                .map(parse_u256)
+                .transpose()?
+                .unwrap_or_default(),
+            gas_limit: gas_limit
+                .as_ref()
+                .map(parse_u64)
+                .transpose()?
+                .unwrap_or(napi_rs_revm_core::DEFAULT_GAS_LIMIT),
+        })
+    }
+}
+
+#[napi(string_enum = "kebab-case")]
+pub enum CallStatus {
+    Success,
+    Revert,
+    Halt,
+}
+
+impl From<napi_rs_revm_core::CallStatus> for CallStatus {
+    fn from(value: napi_rs_revm_core::CallStatus) -> Self {
+        match value {
+            napi_rs_revm_core::CallStatus::Success => Self::Success,
+            napi_rs_revm_core::CallStatus::Revert => Self::Revert,
+            napi_rs_revm_core::CallStatus::Halt => Self::Halt,
+        }
+    }
+}
+
+/// Result of a call or transaction
+#[napi(object)]
+pub struct CallOutcome {
+    pub status: CallStatus,
+    pub gas_used: Quantity,
+    /// Return or revert data as a `0x`-prefixed hex string
+    pub output: String,
+    /// Message of an `Error(string)` or `Panic(uint256)` revert
+    pub revert_reason: Option<String>,
+    pub halt_reason: Option<String>,
+    pub logs: Vec<LogEntry>,
+    /// Address of the contract created by a create transaction
+    pub created_address: Option<String>,
+    /// Execution time of the REVM transaction
+    pub duration_ns: f64,
+}
+
+impl CallOutcome {
+    fn new(value: napi_rs_revm_core::CallOutcome, format: IntegerFormat) -> Self {
+        let napi_rs_revm_core::CallOutcome {
+            status,
+            gas_used,
+            output,
+            revert_reason,
+            halt_reason,
+            logs,
+            created_address,
+            duration_ns,
+        } = value;
+        Self {
+            status: status.into(),
+            gas_used: format.u64(gas_used),
+            output: output.to_string(),
+            revert_reason,
+            halt_reason,
+            logs: logs
+                .into_iter()
+                .map(|log| LogEntry {
+                    address: log.address.to_string(),
+                    topics: log.topics().iter().map(ToString::to_string).collect(),
+                    data: log.data.data.to_string(),
+                })
+                .collect(),
+            created_address: created_address.map(|address| address.to_string()),
+            duration_ns,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct LogEntry {
+    pub address: String,
+    pub topics: Vec<String>,
+    /// `0x`-prefixed hex string
+    pub data: String,
+}
+
+fn parse_address(address: &str) -> Result<Address> {
+    Address::from_str(address)
+        .map_err(|err| Error::from_reason(format!("Invalid address {address:?}: {err}")))
+}
+
+fn parse_bytes(bytes: &str) -> Result<Bytes> {
+    Bytes::from_str(bytes)
+        .map_err(|err| Error::from_reason(format!("Invalid hex {bytes:?}: {err}")))
+}
@@ -0,0 +1,130 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use napi_rs_revm_core::U256;
+use std::str::FromStr;
+
+/// Unsigned integer that may exceed `Number.MAX_SAFE_INTEGER`: a `bigint`, or a `0x`-prefixed
+/// hex string. Decimal strings are accepted as input too
+pub type Quantity = Either<BigInt, String>;
+
+/// How counts, gas and other integers are passed to JS
+#[napi(string_enum = "kebab-case")]
+#[derive(Clone, Copy, Default)]
+pub enum IntegerFormat {
+    #[default]
+    Bigint,
+    /// `0x`-prefixed hex string
+    Hex,
+}
+
+impl IntegerFormat {
+    pub(crate) fn u64(self, value: u64) -> Quantity {
+        match self {
+            IntegerFormat::Bigint => Either::A(value.into()),
+            IntegerFormat::Hex => Either::B(format!("{value:#x}")),
+        }
+    }
+
+    pub(crate) fn u256(self, value: U256) -> Quantity {
+        match self {
+            IntegerFormat::Bigint => Either::A(BigInt {
+                sign_bit: false,
+                words: value.as_limbs().to_vec(),
+            }),
+            IntegerFormat::Hex => Either::B(format!("{value:#x}")),
+        }
+    }
+}
+
+pub(crate) fn parse_u256(value: &Quantity) -> Result<U256> {
+    match value {
+        Either::A(BigInt { sign_bit: true, .. }) => {
+            Err(Error::from_reason("Value must not be negative"))
+        }
+        Either::A(BigInt { words, .. }) => U256::checked_from_limbs_slice(words)
+            .ok_or_else(|| Error::from_reason("Value doesn't fit into 256 bits")),
+        Either::B(value) if value.is_empty() => Err(Error::from_reason(format!(
+            "Invalid value {value:?}: empty string"
+        ))),
+        Either::B(value) => U256::from_str(value)
+            .map_err(|err| Error::from_reason(format!("Invalid value {value:?}: {err}"))),
+    }
+}
+
+pub(crate) fn parse_u64(value: &Quantity) -> Result<u64> {
+    u64::try_from(parse_u256(value)?)
+        .map_err(|_| Error::from_reason("Value doesn't fit into 64 bits"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bigint(sign_bit: bool, words: &[u64]) -> Quantity {
+        Either::A(BigInt {
+            sign_bit,
+            words: words.to_vec(),
+        })
+    }
+
+    #[test]
+    fn test_parse_u256_bigint() {
+        assert_eq!(
+            parse_u256(&bigint(false, &[1, 2])).unwrap(),
+            U256::from_limbs([1, 2, 0, 0])
+        );
+        assert_eq!(
+            parse_u256(&bigint(false, &[u64::MAX; 4])).unwrap(),
+            U256::MAX
+        );
+        // Zero high words don't make the value wider than 256 bits.
+        assert_eq!(
+            parse_u256(&bigint(false, &[7, 0, 0, 0, 0])).unwrap(),
+            U256::from(7)
+        );
+
+        let err = parse_u256(&bigint(true, &[1])).unwrap_err();
+        assert_eq!(err.reason, "Value must not be negative");
+        let err = parse_u256(&bigint(false, &[0, 0, 0, 0, 1])).unwrap_err();
+        assert_eq!(err.reason, "Value doesn't fit into 256 bits");
+    }
+
+    #[test]
+    fn test_parse_u256_string() {
+        let parse = |value: &str| parse_u256(&Either::B(value.to_string()));
+
+        assert_eq!(parse("0x2a").unwrap(), U256::from(42));
+        assert_eq!(parse("42").unwrap(), U256::from(42));
+        assert_eq!(parse(&format!("{:#x}", U256::MAX)).unwrap(), U256::MAX);
+        assert_eq!(parse(&U256::MAX.to_string()).unwrap(), U256::MAX);
+
+        for value in ["-1", "0xzz", "", &format!("0x1{}", "0".repeat(64))] {
+            let err = parse(value).unwrap_err();
+            assert!(
+                err.reason.starts_with(&format!("Invalid value {value:?}")),
+                "{}",
+                err.reason
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_u64() {
+        assert_eq!(
+            parse_u64(&Either::B("0xffffffffffffffff".to_string())).unwrap(),
+            u64::MAX
+        );
+        let err = parse_u64(&Either::B("0x10000000000000000".to_string())).unwrap_err();
+        assert_eq!(err.reason, "Value doesn't fit into 64 bits");
+    }
+
+    #[test]
+    fn test_integer_format_round_trip() {
+        let value = U256::from_limbs([1, 2, 3, 4]);
+        for format in [IntegerFormat::Bigint, IntegerFormat::Hex] {
+            assert_eq!(parse_u256(&format.u256(value)).unwrap(), value);
+            assert_eq!(parse_u64(&format.u64(u64::MAX)).unwrap(), u64::MAX);
+        }
+        assert!(matches!(IntegerFormat::Hex.u64(255), Either::B(value) if value == "0xff"));
+    }
+}
@@ -1,21 +1,408 @@
 #![deny(clippy::all)]
 
-use napi::bindgen_prelude::*;
+use napi::{
+    bindgen_prelude::*,
+    threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
+};
 use napi_derive::napi;
-use std::path::Path;
+use napi_rs_revm_core::{ErrorPhase, TestArtifact, TestError};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+};
+use tokio::sync::Notify;
+
+mod error;
+mod evm;
+mod integer;
+mod runner;
+mod runtime;
+
+use error::{test_error, TestFailure};
+pub use integer::IntegerFormat;
+use integer::Quantity;
+pub use runtime::init_runtime;
+use runtime::run_blocking;
 
 #[napi(object)]
 pub struct TestResult {
     /// Execution time of the REVM transaction
     pub duration_ns: f64,
     pub perf_report: Option<PerfReport>,
+    /// Kind of thread the test was executed on, if it was run through `executeTestOn`
+    pub thread_kind: Option<ThreadKind>,
+    /// Name of the thread the test was executed on
+    pub thread_name: Option<String>,
+    /// CPU the test was running on right before the REVM transaction
+    pub cpu_before: Option<u32>,
+    /// CPU the test was running on right after the REVM transaction
+    pub cpu_after: Option<u32>,
+    /// Sampling profile of the REVM transaction
+    pub profile: Option<ProfileReport>,
+    /// Execution time of the REVM transaction measured with the timestamp counter
+    pub tsc_timing: Option<TscTiming>,
+    /// Time spent in each phase of the test, from loading the artifact to handling the result
+    pub phase_timings: Option<PhaseTimings>,
+    /// Allocations, RSS growth and page faults during the REVM transaction
+    pub memory: Option<MemoryReport>,
+    /// Global allocator the addon was built with
+    pub allocator: Allocator,
+    /// Machine and build the test was executed with
+    pub environment: Environment,
 }
 
-impl From<napi_rs_revm_core::TestResult> for TestResult {
-    fn from(value: napi_rs_revm_core::TestResult) -> Self {
+impl TestResult {
+    fn new(value: napi_rs_revm_core::TestResult, format: IntegerFormat) -> Self {
         Self {
             duration_ns: value.duration_ns,
-            perf_report: value.perf_report.map(PerfReport::from),
+            perf_report: value
+                .perf_report
+                .map(|perf_report| PerfReport::new(perf_report, format)),
+            thread_kind: value.thread_kind.map(ThreadKind::from),
+            thread_name: value.thread_name,
+            cpu_before: value.cpu_before,
+            cpu_after: value.cpu_after,
+            profile: value
+                .profile
+                .map(|profile| ProfileReport::new(profile, format)),
+            tsc_timing: value
+                .tsc_timing
+                .map(|tsc_timing| TscTiming::new(tsc_timing, format)),
+            phase_timings: value.phase_timings.map(PhaseTimings::from),
+            memory: value.memory.map(|memory| MemoryReport::new(memory, format)),
+            allocator: value.allocator.into(),
+            environment: value.environment.into(),
+        }
+    }
+}
+
+/// Description of the machine and build that produced a result
+#[napi(object)]
+pub struct Environment {
+    pub cpu: CpuEnvironment,
+    pub kernel: KernelEnvironment,
+    pub build: BuildEnvironment,
+}
+
+impl From<napi_rs_revm_core::Environment> for Environment {
+    fn from(value: napi_rs_revm_core::Environment) -> Self {
+        let napi_rs_revm_core::Environment { cpu, kernel, build } = value;
+        Self {
+            cpu: cpu.into(),
+            kernel: kernel.into(),
+            build: build.into(),
+        }
+    }
+}
+
+/// CPU model and frequency scaling, from `/proc/cpuinfo` and `/sys/devices/system/cpu`
+#[napi(object)]
+pub struct CpuEnvironment {
+    pub model: Option<String>,
+    pub vendor: Option<String>,
+    pub microcode: Option<String>,
+    /// Online logical CPUs
+    pub logical_cpus: u32,
+    /// Distinct cpufreq governors of the online CPUs, empty if cpufreq isn't available
+    pub scaling_governors: Vec<String>,
+    pub scaling_driver: Option<String>,
+    /// Whether sibling hyperthreads are online
+    pub smt_active: Option<bool>,
+    /// `on`, `off`, `forceoff`, `notsupported` or `notimplemented`
+    pub smt_control: Option<String>,
+    /// Whether turbo boost is enabled
+    pub turbo: Option<bool>,
+}
+
+impl From<napi_rs_revm_core::CpuEnvironment> for CpuEnvironment {
+    fn from(value: napi_rs_revm_core::CpuEnvironment) -> Self {
+        let napi_rs_revm_core::CpuEnvironment {
+            model,
+            vendor,
+            microcode,
+            logical_cpus,
+            scaling_governors,
+            scaling_driver,
+            smt_active,
+            smt_control,
+            turbo,
+        } = value;
+        Self {
+            model,
+            vendor,
+            microcode,
+            logical_cpus,
+            scaling_governors,
+            scaling_driver,
+            smt_active,
+            smt_control,
+            turbo,
+        }
+    }
+}
+
+/// Kernel fields of `uname`
+#[napi(object)]
+pub struct KernelEnvironment {
+    pub sysname: String,
+    pub release: String,
+    pub version: String,
+    pub machine: String,
+}
+
+impl From<napi_rs_revm_core::KernelEnvironment> for KernelEnvironment {
+    fn from(value: napi_rs_revm_core::KernelEnvironment) -> Self {
+        let napi_rs_revm_core::KernelEnvironment {
+            sysname,
+            release,
+            version,
+            machine,
+        } = value;
+        Self {
+            sysname,
+            release,
+            version,
+            machine,
+        }
+    }
+}
+
+/// Build-time metadata of the addon
+#[napi(object)]
+pub struct BuildEnvironment {
+    pub crate_version: String,
+    pub rustc_version: String,
+    /// Cargo profile, `debug` or `release`
+    pub profile: String,
+    pub opt_level: String,
+    pub target: String,
+    pub revm_version: String,
+    /// Enabled cargo features of the core crate
+    pub features: Vec<String>,
+}
+
+impl From<napi_rs_revm_core::BuildEnvironment> for BuildEnvironment {
+    fn from(value: napi_rs_revm_core::BuildEnvironment) -> Self {
+        let napi_rs_revm_core::BuildEnvironment {
+            crate_version,
+            rustc_version,
+            profile,
+            opt_level,
+            target,
+            revm_version,
+            features,
+        } = value;
+        Self {
+            crate_version,
+            rustc_version,
+            profile,
+            opt_level,
+            target,
+            revm_version,
+            features,
+        }
+    }
+}
+
+#[napi(string_enum = "kebab-case")]
+pub enum Allocator {
+    System,
+    Mimalloc,
+    Jemalloc,
+}
+
+impl From<napi_rs_revm_core::Allocator> for Allocator {
+    fn from(value: napi_rs_revm_core::Allocator) -> Self {
+        match value {
+            napi_rs_revm_core::Allocator::System => Self::System,
+            napi_rs_revm_core::Allocator::Mimalloc => Self::Mimalloc,
+            napi_rs_revm_core::Allocator::Jemalloc => Self::Jemalloc,
+        }
+    }
+}
+
+/// Memory accounting of the REVM transaction
+#[napi(object)]
+pub struct MemoryReport {
+    /// Heap allocations of the executing thread, only counted when built with the
+    /// `counting-allocator` feature
+    pub allocations: Option<AllocationStats>,
+    /// Growth of the process' peak resident set size in KiB
+    pub max_rss_delta_kb: Quantity,
+    /// Page faults of the executing thread that were served without I/O
+    pub minor_page_faults: Quantity,
+    /// Page faults of the executing thread that required I/O
+    pub major_page_faults: Quantity,
+}
+
+impl MemoryReport {
+    fn new(value: napi_rs_revm_core::MemoryReport, format: IntegerFormat) -> Self {
+        let napi_rs_revm_core::MemoryReport {
+            allocations,
+            max_rss_delta_kb,
+            minor_page_faults,
+            major_page_faults,
+        } = value;
+        Self {
+            allocations: allocations.map(|allocations| AllocationStats::new(allocations, format)),
+            max_rss_delta_kb: format.u64(max_rss_delta_kb),
+            minor_page_faults: format.u64(minor_page_faults),
+            major_page_faults: format.u64(major_page_faults),
+        }
+    }
+}
+
+/// Heap allocations made by the executing thread. A `realloc` counts as one deallocation and
+/// one allocation
+#[napi(object)]
+pub struct AllocationStats {
+    pub allocations: Quantity,
+    pub deallocations: Quantity,
+    pub bytes_allocated: Quantity,
+    /// Highest number of bytes allocated and not yet freed during the transaction
+    pub peak_bytes: Quantity,
+}
+
+impl AllocationStats {
+    fn new(value: napi_rs_revm_core::AllocationStats, format: IntegerFormat) -> Self {
+        let napi_rs_revm_core::AllocationStats {
+            allocations,
+            deallocations,
+            bytes_allocated,
+            peak_bytes,
+        } = value;
+        Self {
+            allocations: format.u64(allocations),
+            deallocations: format.u64(deallocations),
+            bytes_allocated: format.u64(bytes_allocated),
+            peak_bytes: format.u64(peak_bytes),
+        }
+    }
+}
+
+/// Wall-clock time of each phase of a test execution
+#[napi(object)]
+pub struct PhaseTimings {
+    /// Reading the artifact file and parsing its JSON
+    pub load_ns: f64,
+    /// Hex decoding the deployed bytecode
+    pub decode_ns: f64,
+    /// Jump destination analysis of the bytecode
+    pub analyse_ns: f64,
+    /// Inserting the contract account into the database
+    pub db_ns: f64,
+    /// Building the EVM and the transaction
+    pub build_ns: f64,
+    /// The REVM transaction, same as `durationNs`
+    pub transact_ns: f64,
+    /// Checking the transaction result and dropping it together with the EVM
+    pub result_handling_ns: f64,
+}
+
+impl From<napi_rs_revm_core::PhaseTimings> for PhaseTimings {
+    fn from(value: napi_rs_revm_core::PhaseTimings) -> Self {
+        let napi_rs_revm_core::PhaseTimings {
+            load_ns,
+            decode_ns,
+            analyse_ns,
+            db_ns,
+            build_ns,
+            transact_ns,
+            result_handling_ns,
+        } = value;
+        Self {
+            load_ns,
+            decode_ns,
+            analyse_ns,
+            db_ns,
+            build_ns,
+            transact_ns,
+            result_handling_ns,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct TscTiming {
+    /// Timestamp counter ticks: TSC cycles on x86_64, generic timer ticks on aarch64
+    pub cycles: Quantity,
+    /// `cycles` converted to nanoseconds
+    pub duration_ns: f64,
+    /// Calibrated counter frequency
+    pub frequency_hz: f64,
+}
+
+impl TscTiming {
+    fn new(value: napi_rs_revm_core::TscTiming, format: IntegerFormat) -> Self {
+        let napi_rs_revm_core::TscTiming {
+            cycles,
+            duration_ns,
+            frequency_hz,
+        } = value;
+        Self {
+            cycles: format.u64(cycles),
+            duration_ns,
+            frequency_hz,
+        }
+    }
+}
+
+#[napi(string_enum = "kebab-case")]
+pub enum ThreadKind {
+    CurrentThread,
+    SpawnedThread,
+    TokioBlocking,
+    TokioWorker,
+    Rayon,
+    DedicatedWorker,
+}
+
+impl From<ThreadKind> for napi_rs_revm_core::ThreadKind {
+    fn from(value: ThreadKind) -> Self {
+        match value {
+            ThreadKind::CurrentThread => Self::CurrentThread,
+            ThreadKind::SpawnedThread => Self::SpawnedThread,
+            ThreadKind::TokioBlocking => Self::TokioBlocking,
+            ThreadKind::TokioWorker => Self::TokioWorker,
+            ThreadKind::Rayon => Self::Rayon,
+            ThreadKind::DedicatedWorker => Self::DedicatedWorker,
+        }
+    }
+}
+
+impl From<napi_rs_revm_core::ThreadKind> for ThreadKind {
+    fn from(value: napi_rs_revm_core::ThreadKind) -> Self {
+        match value {
+            napi_rs_revm_core::ThreadKind::CurrentThread => Self::CurrentThread,
+            napi_rs_revm_core::ThreadKind::SpawnedThread => Self::SpawnedThread,
+            napi_rs_revm_core::ThreadKind::TokioBlocking => Self::TokioBlocking,
+            napi_rs_revm_core::ThreadKind::TokioWorker => Self::TokioWorker,
+            napi_rs_revm_core::ThreadKind::Rayon => Self::Rayon,
+            napi_rs_revm_core::ThreadKind::DedicatedWorker => Self::DedicatedWorker,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct ExecutorConfig {
+    pub thread_kind: ThreadKind,
+    /// Stack size in bytes of the spawned thread
+    pub stack_size: Option<u32>,
+}
+
+impl From<ExecutorConfig> for napi_rs_revm_core::ExecutorConfig {
+    fn from(value: ExecutorConfig) -> Self {
+        let ExecutorConfig {
+            thread_kind,
+            stack_size,
+        } = value;
+        Self {
+            thread_kind: thread_kind.into(),
+            stack_size: stack_size.map(|size| size as usize),
         }
     }
 }
@@ -29,10 +416,80 @@ pub struct PerfReportConfig {
     pub l1_instruction_cache_misses: bool,
     pub branch_miss_ratio: bool,
     pub cpu_migrations: bool,
+    pub instruction_tlb_misses: Option<bool>,
+    pub data_tlb_miss_ratio: Option<bool>,
+    pub page_faults: Option<bool>,
+    pub context_switches: Option<bool>,
+    pub task_clock: Option<bool>,
+    pub stalled_cycles: Option<bool>,
+    pub ref_cycles: Option<bool>,
+    /// Raw PMU events reported in `PerfReport.counters`
+    pub raw_events: Option<Vec<RawEventConfig>>,
+    /// Events from the named event table reported in `PerfReport.counters`
+    pub named_events: Option<Vec<String>>,
+    /// Split counters into groups of at most this many hardware events
+    pub max_hardware_events_per_group: Option<u32>,
+    /// How to handle multiplexed counters, `scale` by default
+    pub multiplexing: Option<MultiplexingPolicy>,
+    /// Whether to count events in user space, kernel space or both, `user` by default
+    pub counting_mode: Option<CountingMode>,
+    /// Also count events while the hypervisor runs
+    pub include_hypervisor: Option<bool>,
+}
+
+/// Privilege levels at which events are counted. The kernel only honours them for PMU events
+/// and page faults, see `PerfReport.effectiveCountingModes`
+#[napi(string_enum = "kebab-case")]
+pub enum CountingMode {
+    /// Only count while the thread runs in user space
+    User,
+    /// Only count kernel work on behalf of the thread
+    Kernel,
+    /// Count both user and kernel space
+    UserAndKernel,
 }
 
-impl From<PerfReportConfig> for napi_rs_revm_core::PerfReportConfig {
-    fn from(value: PerfReportConfig) -> Self {
+impl From<napi_rs_revm_core::CountingMode> for CountingMode {
+    fn from(value: napi_rs_revm_core::CountingMode) -> Self {
+        match value {
+            napi_rs_revm_core::CountingMode::User => Self::User,
+            napi_rs_revm_core::CountingMode::Kernel => Self::Kernel,
+            napi_rs_revm_core::CountingMode::UserAndKernel => Self::UserAndKernel,
+        }
+    }
+}
+
+impl From<CountingMode> for napi_rs_revm_core::CountingMode {
+    fn from(value: CountingMode) -> Self {
+        match value {
+            CountingMode::User => Self::User,
+            CountingMode::Kernel => Self::Kernel,
+            CountingMode::UserAndKernel => Self::UserAndKernel,
+        }
+    }
+}
+
+#[napi(string_enum = "kebab-case")]
+pub enum MultiplexingPolicy {
+    /// Extrapolate counts by `time_enabled / time_running`
+    Scale,
+    /// Fail instead of reporting extrapolated counts
+    Reject,
+}
+
+impl From<MultiplexingPolicy> for napi_rs_revm_core::MultiplexingPolicy {
+    fn from(value: MultiplexingPolicy) -> Self {
+        match value {
+            MultiplexingPolicy::Scale => Self::Scale,
+            MultiplexingPolicy::Reject => Self::Reject,
+        }
+    }
+}
+
+impl TryFrom<PerfReportConfig> for napi_rs_revm_core::PerfReportConfig {
+    type Error = Error;
+
+    fn try_from(value: PerfReportConfig) -> Result<Self> {
         let PerfReportConfig {
             instructions,
             instructions_per_cycle,
@@ -41,8 +498,21 @@ impl From<PerfReportConfig> for napi_rs_revm_core::PerfReportConfig {
             l1_instruction_cache_misses,
             branch_miss_ratio,
             cpu_migrations,
+            instruction_tlb_misses,
+            data_tlb_miss_ratio,
+            page_faults,
+            context_switches,
+            task_clock,
+            stalled_cycles,
+            ref_cycles,
+            raw_events,
+            named_events,
+            max_hardware_events_per_group,
+            multiplexing,
+            counting_mode,
+            include_hypervisor,
         } = value;
-        Self {
+        Ok(Self {
             instructions,
             instructions_per_cycle,
             last_level_cache_hit_rate,
@@ -50,23 +520,95 @@ impl From<PerfReportConfig> for napi_rs_revm_core::PerfReportConfig {
             l1_instruction_cache_misses,
             branch_miss_ratio,
             cpu_migrations,
-        }
+            instruction_tlb_misses: instruction_tlb_misses.unwrap_or_default(),
+            data_tlb_miss_ratio: data_tlb_miss_ratio.unwrap_or_default(),
+            page_faults: page_faults.unwrap_or_default(),
+            context_switches: context_switches.unwrap_or_default(),
+            task_clock: task_clock.unwrap_or_default(),
+            stalled_cycles: stalled_cycles.unwrap_or_default(),
+            ref_cycles: ref_cycles.unwrap_or_default(),
+            raw_events: raw_events
+                .unwrap_or_default()
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_>>()?,
+            named_events: named_events.unwrap_or_default(),
+            max_hardware_events_per_group: max_hardware_events_per_group.map(|max| max as usize),
+            multiplexing: multiplexing.map(Into::into).unwrap_or_default(),
+            counting_mode: counting_mode.map(Into::into).unwrap_or_default(),
+            include_hypervisor: include_hypervisor.unwrap_or_default(),
+        })
+    }
+}
+
+#[napi(object)]
+pub struct RawEventConfig {
+    /// Name under which the count is reported in `PerfReport.counters`
+    pub name: String,
+    /// `perf_event_attr.type`, 4 for CPU-specific raw events
+    pub event_type: u32,
+    /// `perf_event_attr.config` as a hexadecimal string
+    pub config: String,
+    /// `perf_event_attr.config1` as a hexadecimal string
+    pub config1: Option<String>,
+}
+
+impl TryFrom<RawEventConfig> for napi_rs_revm_core::RawEventConfig {
+    type Error = Error;
+
+    fn try_from(value: RawEventConfig) -> Result<Self> {
+        let RawEventConfig {
+            name,
+            event_type,
+            config,
+            config1,
+        } = value;
+        let parse_hex = |hex: &str| napi_rs_revm_core::parse_hex(hex).map_err(Error::from_reason);
+        Ok(Self {
+            name,
+            event_type,
+            config: parse_hex(&config)?,
+            config1: config1.as_deref().map(parse_hex).transpose()?.unwrap_or(0),
+        })
     }
 }
 
 #[napi(object)]
 pub struct PerfReport {
-    pub instructions: Option<f64>,
+    pub instructions: Option<Quantity>,
     pub instructions_per_cycle: Option<f64>,
     pub last_level_cache_hit_rate: Option<f64>,
     pub l1_data_cache_hit_rate: Option<f64>,
-    pub l1_instruction_cache_misses: Option<f64>,
+    pub l1_instruction_cache_misses: Option<Quantity>,
     pub branch_miss_ratio: Option<f64>,
-    pub cpu_migrations: Option<f64>,
+    pub cpu_migrations: Option<Quantity>,
+    pub instruction_tlb_misses: Option<Quantity>,
+    pub instruction_tlb_misses_per_kilo_instruction: Option<f64>,
+    pub data_tlb_miss_ratio: Option<f64>,
+    pub page_faults_minor: Option<Quantity>,
+    pub page_faults_major: Option<Quantity>,
+    pub context_switches: Option<Quantity>,
+    pub voluntary_context_switches: Option<Quantity>,
+    pub involuntary_context_switches: Option<Quantity>,
+    pub task_clock_ns: Option<Quantity>,
+    pub cpu_utilization: Option<f64>,
+    pub stalled_cycles_frontend_ratio: Option<f64>,
+    pub stalled_cycles_backend_ratio: Option<f64>,
+    pub ref_cycles: Option<Quantity>,
+    pub cycles_per_ref_cycle: Option<f64>,
+    /// Counts of the requested raw and named events by name
+    pub counters: HashMap<String, Quantity>,
+    /// Share of the enabled time each counter was counting, below 1 if it was multiplexed
+    pub running_ratios: HashMap<String, f64>,
+    /// Requested counters that couldn't be collected on this machine, with the reason
+    pub unavailable: HashMap<String, String>,
+    /// Counters that the kernel counts in a different mode than `countingMode`
+    pub effective_counting_modes: HashMap<String, CountingMode>,
 }
 
-impl From<napi_rs_revm_core::PerfReport> for PerfReport {
-    fn from(value: napi_rs_revm_core::PerfReport) -> Self {
+impl PerfReport {
+    fn new(value: napi_rs_revm_core::PerfReport, format: IntegerFormat) -> Self {
+        let count = |count: Option<u64>| count.map(|count| format.u64(count));
         let napi_rs_revm_core::PerfReport {
             instructions,
             instructions_per_cycle,
@@ -75,49 +617,718 @@ impl From<napi_rs_revm_core::PerfReport> for PerfReport {
             l1_instruction_cache_misses,
             branch_miss_ratio,
             cpu_migrations,
+            instruction_tlb_misses,
+            instruction_tlb_misses_per_kilo_instruction,
+            data_tlb_miss_ratio,
+            page_faults_minor,
+            page_faults_major,
+            context_switches,
+            voluntary_context_switches,
+            involuntary_context_switches,
+            task_clock_ns,
+            cpu_utilization,
+            stalled_cycles_frontend_ratio,
+            stalled_cycles_backend_ratio,
+            ref_cycles,
+            cycles_per_ref_cycle,
+            counters,
+            running_ratios,
+            unavailable,
+            effective_counting_modes,
         } = value;
         Self {
-            instructions,
+            instructions: count(instructions),
             instructions_per_cycle,
             last_level_cache_hit_rate,
             l1_data_cache_hit_rate,
-            l1_instruction_cache_misses,
+            l1_instruction_cache_misses: count(l1_instruction_cache_misses),
             branch_miss_ratio,
-            cpu_migrations,
+            cpu_migrations: count(cpu_migrations),
+            instruction_tlb_misses: count(instruction_tlb_misses),
+            instruction_tlb_misses_per_kilo_instruction,
+            data_tlb_miss_ratio,
+            page_faults_minor: count(page_faults_minor),
+            page_faults_major: count(page_faults_major),
+            context_switches: count(context_switches),
+            voluntary_context_switches: count(voluntary_context_switches),
+            involuntary_context_switches: count(involuntary_context_switches),
+            task_clock_ns: count(task_clock_ns),
+            cpu_utilization,
+            stalled_cycles_frontend_ratio,
+            stalled_cycles_backend_ratio,
+            ref_cycles: count(ref_cycles),
+            cycles_per_ref_cycle,
+            counters: counters
+                .into_iter()
+                .map(|(name, count)| (name, format.u64(count)))
+                .collect(),
+            running_ratios: running_ratios.into_iter().collect(),
+            unavailable: unavailable.into_iter().collect(),
+            effective_counting_modes: effective_counting_modes
+                .into_iter()
+                .map(|(name, mode)| (name, mode.into()))
+                .collect(),
         }
     }
 }
 
-/// Async Node.js wrapper around the core `execute_test` function
+#[napi(object)]
+pub struct SchedulingConfig {
+    /// CPUs to pin the executing thread to
+    pub cpu_affinity: Option<Vec<u32>>,
+    /// Run the executing thread under `SCHED_FIFO` with this priority (1-99)
+    pub fifo_priority: Option<i32>,
+    /// Nice value of the executing thread
+    pub nice: Option<i32>,
+}
+
+impl From<SchedulingConfig> for napi_rs_revm_core::SchedulingConfig {
+    fn from(value: SchedulingConfig) -> Self {
+        let SchedulingConfig {
+            cpu_affinity,
+            fifo_priority,
+            nice,
+        } = value;
+        Self {
+            cpu_affinity: cpu_affinity
+                .map(|cpus| cpus.into_iter().map(|cpu| cpu as usize).collect()),
+            fifo_priority,
+            nice,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct SamplingConfig {
+    /// `cycles` (default), `instructions`, `cpu-clock`, `task-clock`, a named event or a raw event
+    /// as `name=type:config[:config1]`
+    pub event: Option<String>,
+    /// Samples per second
+    pub frequency: Option<u32>,
+    /// Where to write the folded stacks
+    pub folded_path: String,
+    /// Where to write an SVG flamegraph
+    pub flamegraph_path: Option<String>,
+}
+
+impl TryFrom<SamplingConfig> for napi_rs_revm_core::SamplingConfig {
+    type Error = Error;
+
+    fn try_from(value: SamplingConfig) -> Result<Self> {
+        let SamplingConfig {
+            event,
+            frequency,
+            folded_path,
+            flamegraph_path,
+        } = value;
+        Ok(Self {
+            event: napi_rs_revm_core::SamplingConfig::parse_event(
+                event.as_deref().unwrap_or("cycles"),
+            )
+            .map_err(Error::from_reason)?,
+            frequency: frequency.map(u64::from),
+            folded_path: folded_path.into(),
+            flamegraph_path: flamegraph_path.map(Into::into),
+        })
+    }
+}
+
+#[napi(object)]
+pub struct ProfileReport {
+    /// Samples taken during the REVM transaction
+    pub samples: Quantity,
+    /// Samples dropped because the ring buffer was full
+    pub lost_samples: Quantity,
+    pub folded_path: String,
+    /// Path of the SVG flamegraph, only written if samples were taken
+    pub flamegraph_path: Option<String>,
+}
+
+impl ProfileReport {
+    fn new(value: napi_rs_revm_core::ProfileReport, format: IntegerFormat) -> Self {
+        let napi_rs_revm_core::ProfileReport {
+            samples,
+            lost_samples,
+            folded_path,
+            flamegraph_path,
+        } = value;
+        Self {
+            samples: format.u64(samples),
+            lost_samples: format.u64(lost_samples),
+            folded_path: folded_path.to_string_lossy().into_owned(),
+            flamegraph_path: flamegraph_path.map(|path| path.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+/// Options for a test execution in addition to the perf report config
+#[napi(object)]
+#[derive(Default)]
+pub struct ExecuteTestOptions {
+    pub scheduling: Option<SchedulingConfig>,
+    /// Sample call stacks during the REVM transaction and write a flamegraph
+    pub sampling: Option<SamplingConfig>,
+    /// Also time the REVM transaction with the timestamp counter
+    pub tsc_timing: Option<bool>,
+    /// Time each phase of the test separately
+    pub phase_timings: Option<bool>,
+    /// Report allocations, RSS growth and page faults during the REVM transaction
+    pub memory_report: Option<bool>,
+    /// Format of the counts in the result, defaults to `bigint`
+    pub integer_format: Option<IntegerFormat>,
+}
+
+/// Core options of a test execution and the format to pass its result to JS in
+fn core_options(
+    perf_report_config: Option<PerfReportConfig>,
+    options: Option<ExecuteTestOptions>,
+) -> Result<(napi_rs_revm_core::ExecuteTestOptions, IntegerFormat)> {
+    let ExecuteTestOptions {
+        scheduling,
+        sampling,
+        tsc_timing,
+        phase_timings,
+        memory_report,
+        integer_format,
+    } = options.unwrap_or_default();
+    let options = napi_rs_revm_core::ExecuteTestOptions {
+        perf_report_config: perf_report_config.map(TryInto::try_into).transpose()?,
+        scheduling: scheduling.map(Into::into),
+        sampling: sampling.map(TryInto::try_into).transpose()?,
+        tsc_timing: tsc_timing.unwrap_or_default(),
+        phase_timings: phase_timings.unwrap_or_default(),
+        memory_report: memory_report.unwrap_or_default(),
+        cancellation: None,
+    };
+    Ok((options, integer_format.unwrap_or_default()))
+}
+
+/// Summary of a benchmark's samples with outliers and anything that makes them unreliable
+#[napi(object)]
+pub struct BenchmarkStatistics {
+    pub runs: u32,
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    pub std_dev: f64,
+    pub first_quartile: f64,
+    pub third_quartile: f64,
+    pub median_absolute_deviation: f64,
+    /// Samples outside Tukey's fences or with a modified z-score above 3.5
+    pub outliers: Vec<Outlier>,
+    pub warnings: Vec<BenchmarkWarning>,
+}
+
+impl From<napi_rs_revm_core::BenchmarkStatistics> for BenchmarkStatistics {
+    fn from(value: napi_rs_revm_core::BenchmarkStatistics) -> Self {
+        let napi_rs_revm_core::BenchmarkStatistics {
+            runs,
+            mean,
+            median,
+            min,
+            max,
+            std_dev,
+            first_quartile,
+            third_quartile,
+            median_absolute_deviation,
+            outliers,
+            warnings,
+        } = value;
+        Self {
+            runs: runs as u32,
+            mean,
+            median,
+            min,
+            max,
+            std_dev,
+            first_quartile,
+            third_quartile,
+            median_absolute_deviation,
+            outliers: outliers.into_iter().map(Outlier::from).collect(),
+            warnings: warnings.into_iter().map(BenchmarkWarning::from).collect(),
+        }
+    }
+}
+
+#[napi(object)]
+pub struct Outlier {
+    /// Position of the sample in the order the runs were made
+    pub index: u32,
+    pub value: f64,
+    /// Outside `[Q1 - 1.5 IQR, Q3 + 1.5 IQR]`
+    pub tukey: bool,
+    /// Modified z-score based on the median absolute deviation above 3.5
+    pub mad: bool,
+}
+
+impl From<napi_rs_revm_core::Outlier> for Outlier {
+    fn from(value: napi_rs_revm_core::Outlier) -> Self {
+        let napi_rs_revm_core::Outlier {
+            index,
+            value,
+            tukey,
+            mad,
+        } = value;
+        Self {
+            index: index as u32,
+            value,
+            tukey,
+            mad,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct BenchmarkWarning {
+    pub kind: BenchmarkWarningKind,
+    pub message: String,
+}
+
+impl From<napi_rs_revm_core::BenchmarkWarning> for BenchmarkWarning {
+    fn from(value: napi_rs_revm_core::BenchmarkWarning) -> Self {
+        let napi_rs_revm_core::BenchmarkWarning { kind, message } = value;
+        Self {
+            kind: kind.into(),
+            message,
+        }
+    }
+}
+
+#[napi(string_enum = "kebab-case")]
+pub enum BenchmarkWarningKind {
+    Outliers,
+    FrequencyScaling,
+    HighLoadAverage,
+    CpuMigrations,
+}
+
+impl From<napi_rs_revm_core::BenchmarkWarningKind> for BenchmarkWarningKind {
+    fn from(value: napi_rs_revm_core::BenchmarkWarningKind) -> Self {
+        match value {
+            napi_rs_revm_core::BenchmarkWarningKind::Outliers => Self::Outliers,
+            napi_rs_revm_core::BenchmarkWarningKind::FrequencyScaling => Self::FrequencyScaling,
+            napi_rs_revm_core::BenchmarkWarningKind::HighLoadAverage => Self::HighLoadAverage,
+            napi_rs_revm_core::BenchmarkWarningKind::CpuMigrations => Self::CpuMigrations,
+        }
+    }
+}
+
+/// Computes statistics of benchmark samples and warns about outliers, frequency scaling, a high
+/// load average and runs that migrated between CPUs
 #[napi]
-pub async fn execute_test_async(
+pub fn benchmark_statistics(
+    values: Vec<f64>,
+    migrated_runs: Option<u32>,
+) -> Result<BenchmarkStatistics> {
+    napi_rs_revm_core::benchmark_statistics(&values, migrated_runs.unwrap_or_default() as usize)
+        .map(BenchmarkStatistics::from)
+        .map_err(|err| Error::from_reason(err.to_string()))
+}
+
+/// Distribution of benchmark durations recorded in an HDR histogram
+#[napi(object)]
+pub struct LatencyHistogram {
+    pub count: Quantity,
+    pub min_ns: Quantity,
+    pub max_ns: Quantity,
+    pub p50_ns: Quantity,
+    pub p90_ns: Quantity,
+    pub p99_ns: Quantity,
+    pub p999_ns: Quantity,
+    /// The histogram in the HdrHistogram interval log format
+    pub log: String,
+}
+
+impl LatencyHistogram {
+    fn new(value: napi_rs_revm_core::LatencyHistogram, format: IntegerFormat) -> Self {
+        let napi_rs_revm_core::LatencyHistogram {
+            count,
+            min_ns,
+            max_ns,
+            p50_ns,
+            p90_ns,
+            p99_ns,
+            p999_ns,
+            log,
+        } = value;
+        Self {
+            count: format.u64(count),
+            min_ns: format.u64(min_ns),
+            max_ns: format.u64(max_ns),
+            p50_ns: format.u64(p50_ns),
+            p90_ns: format.u64(p90_ns),
+            p99_ns: format.u64(p99_ns),
+            p999_ns: format.u64(p999_ns),
+            log,
+        }
+    }
+}
+
+/// Records durations in nanoseconds in an HDR histogram. The optional `tag` identifies the
+/// histogram in the log and can't contain commas or whitespace. `integer_format` is the format of
+/// the count and durations of the result, defaults to `bigint`
+#[napi]
+pub fn latency_histogram(
+    durations_ns: Vec<f64>,
+    tag: Option<String>,
+    integer_format: Option<IntegerFormat>,
+) -> Result<LatencyHistogram> {
+    let format = integer_format.unwrap_or_default();
+    napi_rs_revm_core::latency_histogram(&durations_ns, tag.as_deref())
+        .map(|histogram| LatencyHistogram::new(histogram, format))
+        .map_err(|err| Error::from_reason(err.to_string()))
+}
+
+/// Async Node.js wrapper around the core `execute_test` function.
+///
+/// Aborting `signal` rejects the promise with an error whose `code` is `ABORTED`. A test that
+/// is still waiting for a thread is rejected right away and skipped, and a running test is
+/// interrupted at the next instruction, which requires inspecting the REVM transaction whenever a
+/// signal is passed.
+#[napi(ts_return_type = "Promise<TestResult>")]
+pub fn execute_test_async<'env>(
+    env: &'env Env,
     test_artifact_path: String,
     test_name: String,
     perf_report_config: Option<PerfReportConfig>,
-) -> Result<TestResult> {
-    let runtime = tokio::runtime::Handle::current();
-    runtime
-        .spawn_blocking(move || {
-            let test_artifact_path = Path::new(&test_artifact_path);
-            let perf_report_config = perf_report_config.map(Into::into);
-            napi_rs_revm_core::execute_test(test_artifact_path, &test_name, perf_report_config)
-                .map(TestResult::from)
-                .map_err(|err| Error::from_reason(err.to_string()))
-        })
-        .await
-        .map_err(|err| Error::from_reason(err.to_string()))?
+    options: Option<ExecuteTestOptions>,
+    signal: Option<Object<'env>>,
+) -> Result<PromiseRaw<'env, TestResult>> {
+    let test_artifact = TestArtifact::Path(test_artifact_path.into());
+    let (options, format) = core_options(perf_report_config, options)?;
+    spawn_test(env, test_artifact, test_name, options, format, signal)
+}
+
+/// Like `execute_test_async`, but takes the artifact from memory instead of a file
+#[napi(ts_return_type = "Promise<TestResult>")]
+pub fn execute_artifact_async<'env>(
+    env: &'env Env,
+    test_artifact: ArtifactInput<'env>,
+    test_name: String,
+    perf_report_config: Option<PerfReportConfig>,
+    options: Option<ExecuteTestOptions>,
+    signal: Option<Object<'env>>,
+) -> Result<PromiseRaw<'env, TestResult>> {
+    let (options, format) = core_options(perf_report_config, options)?;
+    let test_artifact = core_artifact(env, test_artifact, format)?;
+    spawn_test(env, test_artifact, test_name, options, format, signal)
+}
+
+fn spawn_test<'env>(
+    env: &'env Env,
+    test_artifact: TestArtifact,
+    test_name: String,
+    mut options: napi_rs_revm_core::ExecuteTestOptions,
+    format: IntegerFormat,
+    signal: Option<Object<'env>>,
+) -> Result<PromiseRaw<'env, TestResult>> {
+    // Set by whichever comes first: the job starting, or an abort rejecting the promise before
+    // the job got a thread.
+    let started = Arc::new(AtomicBool::new(false));
+    let aborted = Arc::new(Notify::new());
+    if let Some(signal) = signal {
+        let cancellation = napi_rs_revm_core::CancellationToken::new();
+        // `onabort` only fires for aborts after this call.
+        if signal.get_named_property::<bool>("aborted")? {
+            cancellation.cancel();
+            aborted.notify_one();
+        }
+        // SAFETY: `signal` is a live value of the current env, and napi checks that it's an
+        // `AbortSignal`.
+        let abort_signal = unsafe { AbortSignal::from_napi_value(env.raw(), signal.raw())? };
+        let (on_abort, notify_abort) = (cancellation.clone(), aborted.clone());
+        abort_signal.on_abort(move || {
+            on_abort.cancel();
+            notify_abort.notify_one();
+        });
+        options.cancellation = Some(cancellation);
+    }
+
+    let job_started = started.clone();
+    let job = run_blocking(env, move || {
+        if job_started.swap(true, Ordering::AcqRel) {
+            return Err(TestError::Aborted {
+                phase: ErrorPhase::Load,
+            }
+            .into());
+        }
+        napi_rs_revm_core::execute_artifact_test(&test_artifact, &test_name, options)
+            .map(|result| TestResult::new(result, format))
+    })?;
+    let job = async move {
+        tokio::pin!(job);
+        tokio::select! {
+            result = &mut job => result,
+            () = aborted.notified() => {
+                if started.swap(true, Ordering::AcqRel) {
+                    // A running test is interrupted at its next instruction.
+                    job.await
+                } else {
+                    Ok(Err(TestError::Aborted {
+                        phase: ErrorPhase::Load,
+                    }
+                    .into()))
+                }
+            }
+        }
+    };
+    env.spawn_future_with_callback(
+        job,
+        // Errors with properties can only be created on the JS thread.
+        move |env, result| result.map_err(|err| test_error(env, err, format)),
+    )
 }
 
 /// Synchronous Node.js wrapper around the core `execute_test` function
 #[napi]
 pub fn execute_test_sync(
+    env: &Env,
     test_artifact_path: String,
     test_name: String,
     perf_report_config: Option<PerfReportConfig>,
+    options: Option<ExecuteTestOptions>,
 ) -> Result<TestResult> {
     let test_artifact_path = Path::new(&test_artifact_path);
-    let perf_report_config = perf_report_config.map(Into::into);
-    napi_rs_revm_core::execute_test(test_artifact_path, &test_name, perf_report_config)
-        .map(TestResult::from)
-        .map_err(|err| Error::from_reason(err.to_string()))
+    let (options, format) = core_options(perf_report_config, options)?;
+    napi_rs_revm_core::execute_test_with_options(test_artifact_path, &test_name, options)
+        .map(|result| TestResult::new(result, format))
+        .map_err(|err| test_error(env, err, format))
+}
+
+/// Like `execute_test_sync`, but takes the artifact from memory instead of a file
+#[napi]
+pub fn execute_artifact_sync<'env>(
+    env: &'env Env,
+    test_artifact: ArtifactInput<'env>,
+    test_name: String,
+    perf_report_config: Option<PerfReportConfig>,
+    options: Option<ExecuteTestOptions>,
+) -> Result<TestResult> {
+    let (options, format) = core_options(perf_report_config, options)?;
+    let test_artifact = core_artifact(env, test_artifact, format)?;
+    napi_rs_revm_core::execute_artifact_test(&test_artifact, &test_name, options)
+        .map(|result| TestResult::new(result, format))
+        .map_err(|err| test_error(env, err, format))
+}
+
+/// In-memory test artifact: a `Buffer` of deployed bytecode, or a JSON artifact as a string or
+/// object
+type ArtifactInput<'env> = Either3<Buffer, String, Object<'env>>;
+
+fn core_artifact(
+    env: &Env,
+    test_artifact: ArtifactInput,
+    format: IntegerFormat,
+) -> Result<TestArtifact> {
+    match test_artifact {
+        Either3::A(bytecode) => Ok(TestArtifact::Bytecode(bytecode.to_vec().into())),
+        Either3::B(json) => {
+            TestArtifact::from_json_str(&json).map_err(|err| test_error(env, err.into(), format))
+        }
+        Either3::C(json) => Ok(TestArtifact::Json(env.from_js_value(json)?)),
+    }
+}
+
+/// Node.js wrapper around the core `execute_test_on` function that blocks until the test has been
+/// executed on the selected kind of thread. Its threads are shared by all Node worker threads of
+/// the process
+#[napi]
+pub fn execute_test_on(
+    env: &Env,
+    executor: ExecutorConfig,
+    test_artifact_path: String,
+    test_name: String,
+    perf_report_config: Option<PerfReportConfig>,
+    options: Option<ExecuteTestOptions>,
+) -> Result<TestResult> {
+    let executor = executor.into();
+    let test_artifact_path = Path::new(&test_artifact_path);
+    let (options, format) = core_options(perf_report_config, options)?;
+    napi_rs_revm_core::execute_test_on(&executor, test_artifact_path, &test_name, options)
+        .map(|result| TestResult::new(result, format))
+        .map_err(|err| test_error(env, err, format))
+}
+
+/// JS callback that is called with every value and whose return value is ignored
+type ProgressCallback<T> = ThreadsafeFunction<T, (), T, Status, false>;
+
+/// Calls a progress callback from a blocking thread without waiting for the JS thread, and tracks
+/// the calls, so that the promise is only settled once all of them were delivered
+struct Progress<T: JsValuesTupleIntoVec + 'static> {
+    callback: ProgressCallback<T>,
+    /// Cloned into every call and dropped once the JS thread ran it
+    in_flight: mpsc::Sender<()>,
+    delivered: mpsc::Receiver<()>,
+}
+
+impl<T: JsValuesTupleIntoVec + 'static> Progress<T> {
+    fn new(callback: ProgressCallback<T>) -> Self {
+        let (in_flight, delivered) = mpsc::channel();
+        Self {
+            callback,
+            in_flight,
+            delivered,
+        }
+    }
+
+    fn report(&self, value: T) {
+        let in_flight = self.in_flight.clone();
+        self.callback.call_with_return_value(
+            value,
+            ThreadsafeFunctionCallMode::NonBlocking,
+            move |_, _| {
+                drop(in_flight);
+                Ok(())
+            },
+        );
+    }
+
+    /// Blocks until the JS thread ran every call, or dropped it because the env is shutting down
+    fn wait_until_delivered(self) {
+        let Self {
+            in_flight,
+            delivered,
+            ..
+        } = self;
+        drop(in_flight);
+        // Nothing is ever sent, so this returns once the last clone of the sender is dropped.
+        let _ = delivered.recv();
+    }
+}
+
+/// A test of a batch run by `execute_tests_async` or `TestRunner.run_many`
+#[napi(object)]
+pub struct TestCase {
+    pub test_artifact_path: String,
+    pub test_name: String,
+}
+
+impl From<TestCase> for napi_rs_revm_core::TestCase {
+    fn from(value: TestCase) -> Self {
+        let TestCase {
+            test_artifact_path,
+            test_name,
+        } = value;
+        Self {
+            test_artifact_path: test_artifact_path.into(),
+            test_name,
+        }
+    }
+}
+
+/// Outcome of one test of a batch, passed to the callback as soon as the test completes
+#[napi(object)]
+pub struct TestProgress {
+    /// Position of the test in the batch
+    pub index: u32,
+    /// Number of tests in the batch
+    pub total: u32,
+    pub test_artifact_path: String,
+    pub test_name: String,
+    pub result: Option<TestResult>,
+    pub error: Option<TestFailure>,
+}
+
+/// Result of one benchmark iteration, passed to the callback as soon as the iteration completes
+#[napi(object)]
+pub struct IterationProgress {
+    /// Zero-based number of the iteration
+    pub iteration: u32,
+    /// Number of iterations of the benchmark
+    pub iterations: u32,
+    pub result: TestResult,
+}
+
+/// Executes tests one after another on a tokio blocking thread and calls `on_result` with the
+/// outcome of each test as soon as it completes. A failing test doesn't stop the batch. Resolves
+/// with the number of failed tests once `on_result` has returned for every test
+#[napi(ts_return_type = "Promise<number>")]
+pub fn execute_tests_async<'env>(
+    env: &'env Env,
+    tests: Vec<TestCase>,
+    on_result: ProgressCallback<TestProgress>,
+    perf_report_config: Option<PerfReportConfig>,
+    options: Option<ExecuteTestOptions>,
+) -> Result<PromiseRaw<'env, u32>> {
+    let (options, format) = core_options(perf_report_config, options)?;
+    let on_result = Progress::new(on_result);
+    let job = run_blocking(env, move || {
+        let total = tests.len() as u32;
+        let mut failed = 0;
+        for (index, test) in tests.into_iter().enumerate() {
+            let TestCase {
+                test_artifact_path,
+                test_name,
+            } = test;
+            let (result, error) = match napi_rs_revm_core::execute_test_with_options(
+                Path::new(&test_artifact_path),
+                &test_name,
+                options.clone(),
+            ) {
+                Ok(result) => (Some(TestResult::new(result, format)), None),
+                Err(err) => {
+                    failed += 1;
+                    (None, Some(TestFailure::new(&err, format)))
+                }
+            };
+            let progress = TestProgress {
+                index: index as u32,
+                total,
+                test_artifact_path,
+                test_name,
+                result,
+                error,
+            };
+            on_result.report(progress);
+        }
+        on_result.wait_until_delivered();
+        failed
+    })?;
+    env.spawn_future(job)
+}
+
+/// Executes a test `iterations` times on a tokio blocking thread and calls `on_iteration` with the
+/// result of every iteration as soon as it completes. Resolves with the statistics of all
+/// iterations and rejects on the first failing iteration, in both cases only once `on_iteration`
+/// has returned for every completed iteration
+#[napi(ts_return_type = "Promise<BenchmarkStatistics>")]
+pub fn benchmark_test_async<'env>(
+    env: &'env Env,
+    test_artifact_path: String,
+    test_name: String,
+    iterations: u32,
+    on_iteration: ProgressCallback<IterationProgress>,
+    perf_report_config: Option<PerfReportConfig>,
+    options: Option<ExecuteTestOptions>,
+) -> Result<PromiseRaw<'env, BenchmarkStatistics>> {
+    let (options, format) = core_options(perf_report_config, options)?;
+    let on_iteration = Progress::new(on_iteration);
+    let benchmark = move |on_iteration: &Progress<IterationProgress>| {
+        let test_artifact_path = Path::new(&test_artifact_path);
+        let mut results = Vec::with_capacity(iterations as usize);
+        for iteration in 0..iterations {
+            let result = napi_rs_revm_core::execute_test_with_options(
+                test_artifact_path,
+                &test_name,
+                options.clone(),
+            )?;
+            let progress = IterationProgress {
+                iteration,
+                iterations,
+                result: TestResult::new(result.clone(), format),
+            };
+            on_iteration.report(progress);
+            results.push(result);
+        }
+        napi_rs_revm_core::BenchmarkStatistics::from_test_results(&results)
+    };
+    let job = run_blocking(env, move || {
+        let statistics = benchmark(&on_iteration);
+        on_iteration.wait_until_delivered();
+        statistics.map(BenchmarkStatistics::from)
+    })?;
+    env.spawn_future_with_callback(job, move |env, result| {
+        result.map_err(|err| test_error(env, err, format))
+    })
 }
@@ -0,0 +1,127 @@
+use crate::{
+    core_options, run_blocking, test_error, ExecuteTestOptions, IntegerFormat, PerfReportConfig,
+    TestCase, TestFailure, TestResult,
+};
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use napi_rs_revm_core::{WarmUpConfig, WorkerPool, WorkerPoolConfig};
+use std::sync::Arc;
+
+/// Pool of dedicated native worker threads that executes tests in parallel
+#[napi]
+pub struct TestRunner {
+    pool: Arc<WorkerPool>,
+}
+
+#[napi]
+impl TestRunner {
+    /// Spawns the workers and blocks until all of them are pinned and warmed up
+    #[napi(constructor)]
+    pub fn new(env: &Env, options: Option<TestRunnerOptions>) -> Result<Self> {
+        let TestRunnerOptions {
+            size,
+            stack_size,
+            cpu_affinity,
+            warm_up,
+        } = options.unwrap_or_default();
+        let config = WorkerPoolConfig {
+            size: size.map(|size| size as usize),
+            stack_size: stack_size.map(|stack_size| stack_size as usize),
+            cpu_affinity: cpu_affinity
+                .map(|cpus| cpus.into_iter().map(|cpu| cpu as usize).collect()),
+            warm_up: warm_up.map(Into::into),
+        };
+        let pool = WorkerPool::new(config)
+            .map_err(|err| test_error(env, err, IntegerFormat::default()))?;
+        Ok(Self {
+            pool: Arc::new(pool),
+        })
+    }
+
+    /// Number of worker threads
+    #[napi(getter)]
+    pub fn size(&self) -> u32 {
+        self.pool.size() as u32
+    }
+
+    /// Distributes the tests across the workers. Resolves with the outcome of every test in the
+    /// order of `tests` once all of them completed
+    #[napi(ts_return_type = "Promise<Array<TestOutcome>>")]
+    pub fn run_many<'env>(
+        &self,
+        env: &'env Env,
+        tests: Vec<TestCase>,
+        perf_report_config: Option<PerfReportConfig>,
+        options: Option<ExecuteTestOptions>,
+    ) -> Result<PromiseRaw<'env, Vec<TestOutcome>>> {
+        let (options, format) = core_options(perf_report_config, options)?;
+        let pool = self.pool.clone();
+        let tests: Vec<napi_rs_revm_core::TestCase> = tests.into_iter().map(Into::into).collect();
+        let job = run_blocking(env, move || {
+            let results = pool
+                .run_many(tests.clone(), options)
+                .map_err(|err| Error::from_reason(err.to_string()))?;
+            Ok(tests
+                .into_iter()
+                .zip(results)
+                .map(|(test, result)| TestOutcome {
+                    test_artifact_path: test.test_artifact_path.to_string_lossy().into_owned(),
+                    test_name: test.test_name,
+                    error: result
+                        .as_ref()
+                        .err()
+                        .map(|err| TestFailure::new(err, format)),
+                    result: result.ok().map(|result| TestResult::new(result, format)),
+                })
+                .collect())
+        })?;
+        env.spawn_future(async move { job.await? })
+    }
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct TestRunnerOptions {
+    /// Number of worker threads, defaults to the available parallelism
+    pub size: Option<u32>,
+    /// Stack size in bytes of the worker threads
+    pub stack_size: Option<u32>,
+    /// CPUs to pin the workers to, worker `i` is pinned to `cpuAffinity[i % cpuAffinity.length]`
+    pub cpu_affinity: Option<Vec<u32>>,
+    /// Test that every worker executes before taking tests
+    pub warm_up: Option<WarmUpOptions>,
+}
+
+#[napi(object)]
+pub struct WarmUpOptions {
+    pub test_artifact_path: String,
+    pub test_name: String,
+    /// Number of times each worker executes the warm-up test, defaults to 1
+    pub iterations: Option<u32>,
+}
+
+impl From<WarmUpOptions> for WarmUpConfig {
+    fn from(value: WarmUpOptions) -> Self {
+        let WarmUpOptions {
+            test_artifact_path,
+            test_name,
+            iterations,
+        } = value;
+        Self {
+            test: napi_rs_revm_core::TestCase {
+                test_artifact_path: test_artifact_path.into(),
+                test_name,
+            },
+            iterations: iterations.unwrap_or(1) as usize,
+        }
+    }
+}
+
+/// Outcome of a test run by `TestRunner.run_many`
+#[napi(object)]
+pub struct TestOutcome {
+    pub test_artifact_path: String,
+    pub test_name: String,
+    pub result: Option<TestResult>,
+    pub error: Option<TestFailure>,
+}
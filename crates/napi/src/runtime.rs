@@ -0,0 +1,203 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+use tokio::{
+    runtime::{Builder, Handle, Runtime},
+    sync::oneshot,
+};
+
+/// Runtime configured with `init_runtime`. It's the instance data of the env it was configured
+/// in, so every Node worker thread that loads the addon has its own, and it's shut down with the
+/// env
+struct AddonRuntime {
+    handle: Handle,
+    execute_on_workers: bool,
+    owner: RuntimeOwner,
+}
+
+enum RuntimeOwner {
+    MultiThread(Runtime),
+    /// A current-thread runtime is owned by its driver thread, which drops it once this is dropped
+    CurrentThread(oneshot::Sender<()>),
+}
+
+impl AddonRuntime {
+    fn shutdown(self) {
+        match self.owner {
+            // Tests that are still executing must not block the teardown of the env.
+            RuntimeOwner::MultiThread(runtime) => runtime.shutdown_background(),
+            RuntimeOwner::CurrentThread(_shutdown) => {}
+        }
+    }
+}
+
+#[napi(string_enum = "kebab-case")]
+#[derive(Default)]
+pub enum RuntimeFlavor {
+    #[default]
+    MultiThread,
+    /// All async work is driven by a single dedicated thread
+    CurrentThread,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct RuntimeOptions {
+    pub flavor: Option<RuntimeFlavor>,
+    /// Number of worker threads of the multi-thread runtime, defaults to the number of CPUs
+    pub worker_threads: Option<u32>,
+    /// Maximum number of threads of the blocking pool that executes the tests, defaults to 512
+    pub max_blocking_threads: Option<u32>,
+    /// Stack size in bytes of every thread of the runtime
+    pub thread_stack_size: Option<u32>,
+    /// Prefix of the thread names, which are suffixed with the thread's index
+    pub thread_name: Option<String>,
+    /// Every thread of the runtime, workers and blocking threads alike, is pinned to the next CPU
+    /// of this list in turn
+    pub cpu_affinity: Option<Vec<u32>>,
+    /// Execute tests directly on the runtime's worker threads instead of its blocking pool
+    pub execute_on_workers: Option<bool>,
+}
+
+/// Configures the tokio runtime that all async functions of this thread's instance of the addon
+/// execute tests on. Fails if the runtime has already been configured. Without this, tests are
+/// executed on the blocking pool of the runtime of napi-rs
+#[napi]
+pub fn init_runtime(env: &Env, options: Option<RuntimeOptions>) -> Result<()> {
+    let RuntimeOptions {
+        flavor,
+        worker_threads,
+        max_blocking_threads,
+        thread_stack_size,
+        thread_name,
+        cpu_affinity,
+        execute_on_workers,
+    } = options.unwrap_or_default();
+    // Tokio panics on zero, which aborts the process in release builds.
+    for (name, value) in [
+        ("workerThreads", worker_threads),
+        ("maxBlockingThreads", max_blocking_threads),
+        ("threadStackSize", thread_stack_size),
+    ] {
+        if value == Some(0) {
+            return Err(Error::from_reason(format!("{name} must be greater than 0")));
+        }
+    }
+    if env.get_instance_data::<AddonRuntime>()?.is_some() {
+        return Err(Error::from_reason("Runtime is already initialized"));
+    }
+
+    let flavor = flavor.unwrap_or_default();
+    let mut builder = match flavor {
+        RuntimeFlavor::MultiThread => Builder::new_multi_thread(),
+        RuntimeFlavor::CurrentThread => {
+            if worker_threads.is_some() {
+                return Err(Error::from_reason(
+                    "workerThreads only applies to the multi-thread runtime",
+                ));
+            }
+            Builder::new_current_thread()
+        }
+    };
+    builder.enable_all();
+    if let Some(worker_threads) = worker_threads {
+        builder.worker_threads(worker_threads as usize);
+    }
+    if let Some(max_blocking_threads) = max_blocking_threads {
+        builder.max_blocking_threads(max_blocking_threads as usize);
+    }
+    if let Some(thread_stack_size) = thread_stack_size {
+        builder.thread_stack_size(thread_stack_size as usize);
+    }
+    let thread_name = thread_name.unwrap_or_else(|| "revm-runtime".to_string());
+    let driver_name = format!("{thread_name}-driver");
+    let thread_index = AtomicUsize::new(0);
+    builder.thread_name_fn(move || {
+        let index = thread_index.fetch_add(1, Ordering::Relaxed);
+        format!("{thread_name}-{index}")
+    });
+
+    let pin_thread = cpu_affinity.map(pin_round_robin).transpose()?;
+    if let Some(pin_thread) = pin_thread.clone() {
+        builder.on_thread_start(move || pin_thread());
+    }
+    let runtime = builder
+        .build()
+        .map_err(|err| Error::from_reason(err.to_string()))?;
+
+    let handle = runtime.handle().clone();
+    let owner = match flavor {
+        RuntimeFlavor::MultiThread => RuntimeOwner::MultiThread(runtime),
+        RuntimeFlavor::CurrentThread => {
+            let (shutdown, shutdown_requested) = oneshot::channel();
+            // A current-thread runtime only makes progress while a thread blocks on it.
+            thread::Builder::new()
+                .name(driver_name)
+                .spawn(move || {
+                    if let Some(pin_thread) = pin_thread {
+                        pin_thread();
+                    }
+                    // Fails once the sender is dropped, which is the shutdown signal as well.
+                    let _ = runtime.block_on(shutdown_requested);
+                    runtime.shutdown_background();
+                })
+                .map_err(|err| Error::from_reason(err.to_string()))?;
+            RuntimeOwner::CurrentThread(shutdown)
+        }
+    };
+    env.set_instance_data(
+        AddonRuntime {
+            handle,
+            execute_on_workers: execute_on_workers.unwrap_or_default(),
+            owner,
+        },
+        (),
+        |context| context.value.shutdown(),
+    )
+}
+
+/// Thread start hook that pins each thread to the next CPU of `cpus`
+fn pin_round_robin(cpus: Vec<u32>) -> Result<Arc<dyn Fn() + Send + Sync>> {
+    let cpus: Vec<_> = cpus.into_iter().map(|cpu| cpu as usize).collect();
+    // The hook can't fail the thread start, so the CPUs are checked up front. The runtime threads
+    // inherit the affinity mask of this thread.
+    napi_rs_revm_core::check_cpus_allowed(&cpus)
+        .map_err(|err| Error::from_reason(format!("Invalid CPU affinity: {err}")))?;
+    let next_cpu = AtomicUsize::new(0);
+    Ok(Arc::new(move || {
+        let cpu = cpus[next_cpu.fetch_add(1, Ordering::Relaxed) % cpus.len()];
+        let _ = napi_rs_revm_core::set_thread_affinity(&[cpu]);
+    }))
+}
+
+/// Runs a blocking job on the runtime that `init_runtime` configured for `env`, or on the blocking
+/// pool of the napi-rs runtime if none was configured. The runtime is looked up on the JS thread,
+/// the returned future can be awaited anywhere
+pub(crate) fn run_blocking<T, F>(
+    env: &Env,
+    job: F,
+) -> Result<impl Future<Output = Result<T>> + Send + 'static>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let runtime = env
+        .get_instance_data::<AddonRuntime>()?
+        .map(|runtime| (runtime.handle.clone(), runtime.execute_on_workers));
+    Ok(async move {
+        let task = match runtime {
+            Some((handle, true)) => handle.spawn(async move { job() }),
+            Some((handle, false)) => handle.spawn_blocking(job),
+            None => tokio::task::spawn_blocking(job),
+        };
+        task.await
+            .map_err(|err| Error::from_reason(err.to_string()))
+    })
+}
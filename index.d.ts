@@ -1,19 +1,430 @@
 /* auto-generated by NAPI-RS */
 /* eslint-disable */
-/** Async Node.js wrapper around the core `execute_test` function */
-export declare function executeTestAsync(testArtifactPath: string, testName: string, perfReportConfig?: PerfReportConfig | undefined | null): Promise<TestResult>
+/** Long-lived REVM instance with an in-memory database that transactions are committed to */
+export declare class Evm {
+  constructor(options?: EvmOptions | undefined | null)
+  /**
+   * Runs the creation code of a Solidity artifact and commits the created contract, whose
+   * address is `createdAddress` of the outcome
+   */
+  deployArtifact(artifactPath: string, options?: DeployOptions | undefined | null): CallOutcome
+  /**
+   * Like `deploy_artifact`, but takes the artifact from memory instead of a file. A `Buffer` of
+   * deployed bytecode is deployed as is, without a constructor
+   */
+  deployArtifactFromMemory(artifact: Buffer | string | object, options?: DeployOptions | undefined | null): CallOutcome
+  /** Replaces the balance, nonce and code of an account. Its storage is kept */
+  setAccount(address: string, account: AccountInput): void
+  /** Sets a storage slot */
+  setStorage(address: string, slot: Quantity, value: Quantity): void
+  /** The account at `address`, or `null` if it doesn't exist */
+  getAccount(address: string): AccountState | null
+  /** Value of a storage slot */
+  getStorage(address: string, slot: Quantity): Quantity
+  /** Executes a transaction without committing its state changes */
+  call(request: CallRequest): CallOutcome
+  /** Executes a transaction and commits its state changes */
+  transact(request: CallRequest): CallOutcome
+  /** Saves the current state and returns an id to revert to */
+  snapshot(): number
+  /**
+   * Restores the state saved by `snapshot`. The snapshot and all snapshots taken after it are
+   * discarded
+   */
+  revert(snapshotId: number): void
+}
+
+/** Pool of dedicated native worker threads that executes tests in parallel */
+export declare class TestRunner {
+  /** Spawns the workers and blocks until all of them are pinned and warmed up */
+  constructor(options?: TestRunnerOptions | undefined | null)
+  /** Number of worker threads */
+  get size(): number
+  /**
+   * Distributes the tests across the workers. Resolves with the outcome of every test in the
+   * order of `tests` once all of them completed
+   */
+  runMany(tests: Array<TestCase>, perfReportConfig?: PerfReportConfig | undefined | null, options?: ExecuteTestOptions | undefined | null): Promise<Array<TestOutcome>>
+}
+
+/** Account to set. Missing fields are zero or empty */
+export interface AccountInput {
+  balance?: Quantity
+  nonce?: Quantity
+  /** Deployed bytecode as a hex string */
+  code?: string
+}
+
+export interface AccountState {
+  balance: Quantity
+  nonce: Quantity
+  /** Deployed bytecode as a `0x`-prefixed hex string */
+  code: string
+}
+
+/**
+ * Heap allocations made by the executing thread. A `realloc` counts as one deallocation and
+ * one allocation
+ */
+export interface AllocationStats {
+  allocations: Quantity
+  deallocations: Quantity
+  bytesAllocated: Quantity
+  /** Highest number of bytes allocated and not yet freed during the transaction */
+  peakBytes: Quantity
+}
+
+export declare enum Allocator {
+  System = 'system',
+  Mimalloc = 'mimalloc',
+  Jemalloc = 'jemalloc'
+}
+
+/** Summary of a benchmark's samples with outliers and anything that makes them unreliable */
+export interface BenchmarkStatistics {
+  runs: number
+  mean: number
+  median: number
+  min: number
+  max: number
+  stdDev: number
+  firstQuartile: number
+  thirdQuartile: number
+  medianAbsoluteDeviation: number
+  /** Samples outside Tukey's fences or with a modified z-score above 3.5 */
+  outliers: Array<Outlier>
+  warnings: Array<BenchmarkWarning>
+}
+
+/**
+ * Computes statistics of benchmark samples and warns about outliers, frequency scaling, a high
+ * load average and runs that migrated between CPUs
+ */
+export declare function benchmarkStatistics(values: Array<number>, migratedRuns?: number | undefined | null): BenchmarkStatistics
+
+/**
+ * Executes a test `iterations` times on a tokio blocking thread and calls `on_iteration` with the
+ * result of every iteration as soon as it completes. Resolves with the statistics of all
+ * iterations and rejects on the first failing iteration, in both cases only once `on_iteration`
+ * has returned for every completed iteration
+ */
+export declare function benchmarkTestAsync(testArtifactPath: string, testName: string, iterations: number, onIteration: ((arg: IterationProgress) => void), perfReportConfig?: PerfReportConfig | undefined | null, options?: ExecuteTestOptions | undefined | null): Promise<BenchmarkStatistics>
+
+export interface BenchmarkWarning {
+  kind: BenchmarkWarningKind
+  message: string
+}
+
+export declare enum BenchmarkWarningKind {
+  Outliers = 'outliers',
+  FrequencyScaling = 'frequency-scaling',
+  HighLoadAverage = 'high-load-average',
+  CpuMigrations = 'cpu-migrations'
+}
+
+/** Build-time metadata of the addon */
+export interface BuildEnvironment {
+  crateVersion: string
+  rustcVersion: string
+  /** Cargo profile, `debug` or `release` */
+  profile: string
+  optLevel: string
+  target: string
+  revmVersion: string
+  /** Enabled cargo features of the core crate */
+  features: Array<string>
+}
+
+/** Result of a call or transaction */
+export interface CallOutcome {
+  status: CallStatus
+  gasUsed: Quantity
+  /** Return or revert data as a `0x`-prefixed hex string */
+  output: string
+  /** Message of an `Error(string)` or `Panic(uint256)` revert */
+  revertReason?: string
+  haltReason?: string
+  logs: Array<LogEntry>
+  /** Address of the contract created by a create transaction */
+  createdAddress?: string
+  /** Execution time of the REVM transaction */
+  durationNs: number
+}
+
+/** A transaction to call or create a contract */
+export interface CallRequest {
+  /** Defaults to the zero address */
+  caller?: string
+  /** Contract to call, or missing to create a contract from `data` */
+  to?: string
+  /** Calldata or creation code as a hex string */
+  data?: string
+  /** Wei */
+  value?: Quantity
+  /** Defaults to 30 million */
+  gasLimit?: Quantity
+}
+
+export declare enum CallStatus {
+  Success = 'success',
+  Revert = 'revert',
+  Halt = 'halt'
+}
+
+/**
+ * Privilege levels at which events are counted. The kernel only honours them for PMU events
+ * and page faults, see `PerfReport.effectiveCountingModes`
+ */
+export declare enum CountingMode {
+  /** Only count while the thread runs in user space */
+  User = 'user',
+  /** Only count kernel work on behalf of the thread */
+  Kernel = 'kernel',
+  /** Count both user and kernel space */
+  UserAndKernel = 'user-and-kernel'
+}
+
+/** CPU model and frequency scaling, from `/proc/cpuinfo` and `/sys/devices/system/cpu` */
+export interface CpuEnvironment {
+  model?: string
+  vendor?: string
+  microcode?: string
+  /** Online logical CPUs */
+  logicalCpus: number
+  /** Distinct cpufreq governors of the online CPUs, empty if cpufreq isn't available */
+  scalingGovernors: Array<string>
+  scalingDriver?: string
+  /** Whether sibling hyperthreads are online */
+  smtActive?: boolean
+  /** `on`, `off`, `forceoff`, `notsupported` or `notimplemented` */
+  smtControl?: string
+  /** Whether turbo boost is enabled */
+  turbo?: boolean
+}
+
+export interface DeployOptions {
+  /** Defaults to the zero address */
+  caller?: string
+  /** ABI-encoded constructor arguments as a hex string */
+  constructorArgs?: string
+}
+
+/** Description of the machine and build that produced a result */
+export interface Environment {
+  cpu: CpuEnvironment
+  kernel: KernelEnvironment
+  build: BuildEnvironment
+}
+
+/** Stable identifier of a failure, set as `code` of the thrown errors */
+export declare enum ErrorCode {
+  ArtifactNotFound = 'ARTIFACT_NOT_FOUND',
+  ArtifactUnreadable = 'ARTIFACT_UNREADABLE',
+  InvalidArtifact = 'INVALID_ARTIFACT',
+  InvalidBytecode = 'INVALID_BYTECODE',
+  UnknownTest = 'UNKNOWN_TEST',
+  SchedulingFailed = 'SCHEDULING_FAILED',
+  PerfEventsUnavailable = 'PERF_EVENTS_UNAVAILABLE',
+  InstrumentationFailed = 'INSTRUMENTATION_FAILED',
+  EvmFailed = 'EVM_FAILED',
+  TestReverted = 'TEST_REVERTED',
+  TestHalted = 'TEST_HALTED',
+  Aborted = 'ABORTED',
+  /** Any failure that isn't specific to a test, e.g. a worker that panicked */
+  Internal = 'INTERNAL'
+}
+
+/** Phase of a test execution that a failure occurred in */
+export declare enum ErrorPhase {
+  Load = 'load',
+  Decode = 'decode',
+  Analyse = 'analyse',
+  Build = 'build',
+  /** Scheduling and the instrumentation around the REVM transaction */
+  Setup = 'setup',
+  Transact = 'transact',
+  ResultHandling = 'result-handling'
+}
+
+export interface EvmOptions {
+  /** Format of balances, nonces, storage values and gas, defaults to `bigint` */
+  integerFormat?: IntegerFormat
+}
+
+/** Like `execute_test_async`, but takes the artifact from memory instead of a file */
+export declare function executeArtifactAsync(testArtifact: Buffer | string | object, testName: string, perfReportConfig?: PerfReportConfig | undefined | null, options?: ExecuteTestOptions | undefined | null, signal?: AbortSignal | undefined | null): Promise<TestResult>
+
+/** Like `execute_test_sync`, but takes the artifact from memory instead of a file */
+export declare function executeArtifactSync(testArtifact: Buffer | string | object, testName: string, perfReportConfig?: PerfReportConfig | undefined | null, options?: ExecuteTestOptions | undefined | null): TestResult
+
+/**
+ * Async Node.js wrapper around the core `execute_test` function.
+ *
+ * Aborting `signal` rejects the promise with an error whose `code` is `ABORTED`. A test that
+ * is still waiting for a thread is rejected right away and skipped, and a running test is
+ * interrupted at the next instruction, which requires inspecting the REVM transaction whenever a
+ * signal is passed.
+ */
+export declare function executeTestAsync(testArtifactPath: string, testName: string, perfReportConfig?: PerfReportConfig | undefined | null, options?: ExecuteTestOptions | undefined | null, signal?: AbortSignal | undefined | null): Promise<TestResult>
+
+/**
+ * Node.js wrapper around the core `execute_test_on` function that blocks until the test has been
+ * executed on the selected kind of thread. Its threads are shared by all Node worker threads of
+ * the process
+ */
+export declare function executeTestOn(executor: ExecutorConfig, testArtifactPath: string, testName: string, perfReportConfig?: PerfReportConfig | undefined | null, options?: ExecuteTestOptions | undefined | null): TestResult
+
+/** Options for a test execution in addition to the perf report config */
+export interface ExecuteTestOptions {
+  scheduling?: SchedulingConfig
+  /** Sample call stacks during the REVM transaction and write a flamegraph */
+  sampling?: SamplingConfig
+  /** Also time the REVM transaction with the timestamp counter */
+  tscTiming?: boolean
+  /** Time each phase of the test separately */
+  phaseTimings?: boolean
+  /** Report allocations, RSS growth and page faults during the REVM transaction */
+  memoryReport?: boolean
+  /** Format of the counts in the result, defaults to `bigint` */
+  integerFormat?: IntegerFormat
+}
+
+/**
+ * Executes tests one after another on a tokio blocking thread and calls `on_result` with the
+ * outcome of each test as soon as it completes. A failing test doesn't stop the batch. Resolves
+ * with the number of failed tests once `on_result` has returned for every test
+ */
+export declare function executeTestsAsync(tests: Array<TestCase>, onResult: ((arg: TestProgress) => void), perfReportConfig?: PerfReportConfig | undefined | null, options?: ExecuteTestOptions | undefined | null): Promise<number>
 
 /** Synchronous Node.js wrapper around the core `execute_test` function */
-export declare function executeTestSync(testArtifactPath: string, testName: string, perfReportConfig?: PerfReportConfig | undefined | null): TestResult
+export declare function executeTestSync(testArtifactPath: string, testName: string, perfReportConfig?: PerfReportConfig | undefined | null, options?: ExecuteTestOptions | undefined | null): TestResult
+
+export interface ExecutorConfig {
+  threadKind: ThreadKind
+  /** Stack size in bytes of the spawned thread */
+  stackSize?: number
+}
+
+/**
+ * Configures the tokio runtime that all async functions of this thread's instance of the addon
+ * execute tests on. Fails if the runtime has already been configured. Without this, tests are
+ * executed on the blocking pool of the runtime of napi-rs
+ */
+export declare function initRuntime(options?: RuntimeOptions | undefined | null): void
+
+/** How counts, gas and other integers are passed to JS */
+export declare enum IntegerFormat {
+  Bigint = 'bigint',
+  /** `0x`-prefixed hex string */
+  Hex = 'hex'
+}
+
+/** Result of one benchmark iteration, passed to the callback as soon as the iteration completes */
+export interface IterationProgress {
+  /** Zero-based number of the iteration */
+  iteration: number
+  /** Number of iterations of the benchmark */
+  iterations: number
+  result: TestResult
+}
+
+/** Kernel fields of `uname` */
+export interface KernelEnvironment {
+  sysname: string
+  release: string
+  version: string
+  machine: string
+}
+
+/** Distribution of benchmark durations recorded in an HDR histogram */
+export interface LatencyHistogram {
+  count: Quantity
+  minNs: Quantity
+  maxNs: Quantity
+  p50Ns: Quantity
+  p90Ns: Quantity
+  p99Ns: Quantity
+  p999Ns: Quantity
+  /** The histogram in the HdrHistogram interval log format */
+  log: string
+}
+
+/**
+ * Records durations in nanoseconds in an HDR histogram. The optional `tag` identifies the
+ * histogram in the log and can't contain commas or whitespace. `integer_format` is the format of
+ * the count and durations of the result, defaults to `bigint`
+ */
+export declare function latencyHistogram(durationsNs: Array<number>, tag?: string | undefined | null, integerFormat?: IntegerFormat | undefined | null): LatencyHistogram
+
+export interface LogEntry {
+  address: string
+  topics: Array<string>
+  /** `0x`-prefixed hex string */
+  data: string
+}
+
+/** Memory accounting of the REVM transaction */
+export interface MemoryReport {
+  /**
+   * Heap allocations of the executing thread, only counted when built with the
+   * `counting-allocator` feature
+   */
+  allocations?: AllocationStats
+  /** Growth of the process' peak resident set size in KiB */
+  maxRssDeltaKb: Quantity
+  /** Page faults of the executing thread that were served without I/O */
+  minorPageFaults: Quantity
+  /** Page faults of the executing thread that required I/O */
+  majorPageFaults: Quantity
+}
+
+export declare enum MultiplexingPolicy {
+  /** Extrapolate counts by `time_enabled / time_running` */
+  Scale = 'scale',
+  /** Fail instead of reporting extrapolated counts */
+  Reject = 'reject'
+}
+
+export interface Outlier {
+  /** Position of the sample in the order the runs were made */
+  index: number
+  value: number
+  /** Outside `[Q1 - 1.5 IQR, Q3 + 1.5 IQR]` */
+  tukey: boolean
+  /** Modified z-score based on the median absolute deviation above 3.5 */
+  mad: boolean
+}
 
 export interface PerfReport {
-  instructions?: number
+  instructions?: Quantity
   instructionsPerCycle?: number
   lastLevelCacheHitRate?: number
   l1DataCacheHitRate?: number
-  l1InstructionCacheMisses?: number
+  l1InstructionCacheMisses?: Quantity
   branchMissRatio?: number
-  cpuMigrations?: number
+  cpuMigrations?: Quantity
+  instructionTlbMisses?: Quantity
+  instructionTlbMissesPerKiloInstruction?: number
+  dataTlbMissRatio?: number
+  pageFaultsMinor?: Quantity
+  pageFaultsMajor?: Quantity
+  contextSwitches?: Quantity
+  voluntaryContextSwitches?: Quantity
+  involuntaryContextSwitches?: Quantity
+  taskClockNs?: Quantity
+  cpuUtilization?: number
+  stalledCyclesFrontendRatio?: number
+  stalledCyclesBackendRatio?: number
+  refCycles?: Quantity
+  cyclesPerRefCycle?: number
+  /** Counts of the requested raw and named events by name */
+  counters: Record<string, Quantity>
+  /** Share of the enabled time each counter was counting, below 1 if it was multiplexed */
+  runningRatios: Record<string, number>
+  /** Requested counters that couldn't be collected on this machine, with the reason */
+  unavailable: Record<string, string>
+  /** Counters that the kernel counts in a different mode than `countingMode` */
+  effectiveCountingModes: Record<string, CountingMode>
 }
 
 export interface PerfReportConfig {
@@ -24,10 +435,223 @@ export interface PerfReportConfig {
   l1InstructionCacheMisses: boolean
   branchMissRatio: boolean
   cpuMigrations: boolean
+  instructionTlbMisses?: boolean
+  dataTlbMissRatio?: boolean
+  pageFaults?: boolean
+  contextSwitches?: boolean
+  taskClock?: boolean
+  stalledCycles?: boolean
+  refCycles?: boolean
+  /** Raw PMU events reported in `PerfReport.counters` */
+  rawEvents?: Array<RawEventConfig>
+  /** Events from the named event table reported in `PerfReport.counters` */
+  namedEvents?: Array<string>
+  /** Split counters into groups of at most this many hardware events */
+  maxHardwareEventsPerGroup?: number
+  /** How to handle multiplexed counters, `scale` by default */
+  multiplexing?: MultiplexingPolicy
+  /** Whether to count events in user space, kernel space or both, `user` by default */
+  countingMode?: CountingMode
+  /** Also count events while the hypervisor runs */
+  includeHypervisor?: boolean
+}
+
+/** Wall-clock time of each phase of a test execution */
+export interface PhaseTimings {
+  /** Reading the artifact file and parsing its JSON */
+  loadNs: number
+  /** Hex decoding the deployed bytecode */
+  decodeNs: number
+  /** Jump destination analysis of the bytecode */
+  analyseNs: number
+  /** Inserting the contract account into the database */
+  dbNs: number
+  /** Building the EVM and the transaction */
+  buildNs: number
+  /** The REVM transaction, same as `durationNs` */
+  transactNs: number
+  /** Checking the transaction result and dropping it together with the EVM */
+  resultHandlingNs: number
+}
+
+export interface ProfileReport {
+  /** Samples taken during the REVM transaction */
+  samples: Quantity
+  /** Samples dropped because the ring buffer was full */
+  lostSamples: Quantity
+  foldedPath: string
+  /** Path of the SVG flamegraph, only written if samples were taken */
+  flamegraphPath?: string
+}
+
+/**
+ * Unsigned integer that may exceed `Number.MAX_SAFE_INTEGER`: a `bigint`, or a `0x`-prefixed
+ * hex string. Decimal strings are accepted as input too
+ */
+export type Quantity = bigint | string
+
+export interface RawEventConfig {
+  /** Name under which the count is reported in `PerfReport.counters` */
+  name: string
+  /** `perf_event_attr.type`, 4 for CPU-specific raw events */
+  eventType: number
+  /** `perf_event_attr.config` as a hexadecimal string */
+  config: string
+  /** `perf_event_attr.config1` as a hexadecimal string */
+  config1?: string
+}
+
+export declare enum RuntimeFlavor {
+  MultiThread = 'multi-thread',
+  /** All async work is driven by a single dedicated thread */
+  CurrentThread = 'current-thread'
+}
+
+export interface RuntimeOptions {
+  flavor?: RuntimeFlavor
+  /** Number of worker threads of the multi-thread runtime, defaults to the number of CPUs */
+  workerThreads?: number
+  /** Maximum number of threads of the blocking pool that executes the tests, defaults to 512 */
+  maxBlockingThreads?: number
+  /** Stack size in bytes of every thread of the runtime */
+  threadStackSize?: number
+  /** Prefix of the thread names, which are suffixed with the thread's index */
+  threadName?: string
+  /**
+   * Every thread of the runtime, workers and blocking threads alike, is pinned to the next CPU
+   * of this list in turn
+   */
+  cpuAffinity?: Array<number>
+  /** Execute tests directly on the runtime's worker threads instead of its blocking pool */
+  executeOnWorkers?: boolean
+}
+
+export interface SamplingConfig {
+  /**
+   * `cycles` (default), `instructions`, `cpu-clock`, `task-clock`, a named event or a raw event
+   * as `name=type:config[:config1]`
+   */
+  event?: string
+  /** Samples per second */
+  frequency?: number
+  /** Where to write the folded stacks */
+  foldedPath: string
+  /** Where to write an SVG flamegraph */
+  flamegraphPath?: string
+}
+
+export interface SchedulingConfig {
+  /** CPUs to pin the executing thread to */
+  cpuAffinity?: Array<number>
+  /** Run the executing thread under `SCHED_FIFO` with this priority (1-99) */
+  fifoPriority?: number
+  /** Nice value of the executing thread */
+  nice?: number
+}
+
+/** A test of a batch run by `execute_tests_async` or `TestRunner.run_many` */
+export interface TestCase {
+  testArtifactPath: string
+  testName: string
+}
+
+/** Why a test failed. Errors thrown by test executions carry the same fields as properties */
+export interface TestFailure {
+  code: ErrorCode
+  phase?: ErrorPhase
+  message: string
+  /** Artifact that couldn't be loaded */
+  path?: string
+  /** Test function that the artifact doesn't have */
+  testName?: string
+  /** Decoded `Error(string)` or `Panic(uint256)` of a reverted test */
+  revertReason?: string
+  /** Hex encoded output of a reverted test */
+  output?: string
+  /** Gas used by a reverted or halted test */
+  gasUsed?: Quantity
+  /** Exceptional halt of a halted test, e.g. `OutOfGas(Basic)` */
+  haltReason?: string
+}
+
+/** Outcome of a test run by `TestRunner.run_many` */
+export interface TestOutcome {
+  testArtifactPath: string
+  testName: string
+  result?: TestResult
+  error?: TestFailure
+}
+
+/** Outcome of one test of a batch, passed to the callback as soon as the test completes */
+export interface TestProgress {
+  /** Position of the test in the batch */
+  index: number
+  /** Number of tests in the batch */
+  total: number
+  testArtifactPath: string
+  testName: string
+  result?: TestResult
+  error?: TestFailure
 }
 
 export interface TestResult {
   /** Execution time of the REVM transaction */
   durationNs: number
   perfReport?: PerfReport
+  /** Kind of thread the test was executed on, if it was run through `executeTestOn` */
+  threadKind?: ThreadKind
+  /** Name of the thread the test was executed on */
+  threadName?: string
+  /** CPU the test was running on right before the REVM transaction */
+  cpuBefore?: number
+  /** CPU the test was running on right after the REVM transaction */
+  cpuAfter?: number
+  /** Sampling profile of the REVM transaction */
+  profile?: ProfileReport
+  /** Execution time of the REVM transaction measured with the timestamp counter */
+  tscTiming?: TscTiming
+  /** Time spent in each phase of the test, from loading the artifact to handling the result */
+  phaseTimings?: PhaseTimings
+  /** Allocations, RSS growth and page faults during the REVM transaction */
+  memory?: MemoryReport
+  /** Global allocator the addon was built with */
+  allocator: Allocator
+  /** Machine and build the test was executed with */
+  environment: Environment
+}
+
+export interface TestRunnerOptions {
+  /** Number of worker threads, defaults to the available parallelism */
+  size?: number
+  /** Stack size in bytes of the worker threads */
+  stackSize?: number
+  /** CPUs to pin the workers to, worker `i` is pinned to `cpuAffinity[i % cpuAffinity.length]` */
+  cpuAffinity?: Array<number>
+  /** Test that every worker executes before taking tests */
+  warmUp?: WarmUpOptions
+}
+
+export declare enum ThreadKind {
+  CurrentThread = 'current-thread',
+  SpawnedThread = 'spawned-thread',
+  TokioBlocking = 'tokio-blocking',
+  TokioWorker = 'tokio-worker',
+  Rayon = 'rayon',
+  DedicatedWorker = 'dedicated-worker'
+}
+
+export interface TscTiming {
+  /** Timestamp counter ticks: TSC cycles on x86_64, generic timer ticks on aarch64 */
+  cycles: Quantity
+  /** `cycles` converted to nanoseconds */
+  durationNs: number
+  /** Calibrated counter frequency */
+  frequencyHz: number
+}
+
+export interface WarmUpOptions {
+  testArtifactPath: string
+  testName: string
+  /** Number of times each worker executes the warm-up test, defaults to 1 */
+  iterations?: number
 }
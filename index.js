@@ -572,5 +572,25 @@ if (!nativeBinding) {
 }
 
 module.exports = nativeBinding
+module.exports.Evm = nativeBinding.Evm
+module.exports.TestRunner = nativeBinding.TestRunner
+module.exports.benchmarkStatistics = nativeBinding.benchmarkStatistics
+module.exports.benchmarkTestAsync = nativeBinding.benchmarkTestAsync
+module.exports.executeArtifactAsync = nativeBinding.executeArtifactAsync
+module.exports.executeArtifactSync = nativeBinding.executeArtifactSync
 module.exports.executeTestAsync = nativeBinding.executeTestAsync
+module.exports.executeTestOn = nativeBinding.executeTestOn
+module.exports.executeTestsAsync = nativeBinding.executeTestsAsync
 module.exports.executeTestSync = nativeBinding.executeTestSync
+module.exports.initRuntime = nativeBinding.initRuntime
+module.exports.latencyHistogram = nativeBinding.latencyHistogram
+module.exports.Allocator = nativeBinding.Allocator
+module.exports.BenchmarkWarningKind = nativeBinding.BenchmarkWarningKind
+module.exports.CallStatus = nativeBinding.CallStatus
+module.exports.CountingMode = nativeBinding.CountingMode
+module.exports.ErrorCode = nativeBinding.ErrorCode
+module.exports.ErrorPhase = nativeBinding.ErrorPhase
+module.exports.IntegerFormat = nativeBinding.IntegerFormat
+module.exports.MultiplexingPolicy = nativeBinding.MultiplexingPolicy
+module.exports.RuntimeFlavor = nativeBinding.RuntimeFlavor
+module.exports.ThreadKind = nativeBinding.ThreadKind
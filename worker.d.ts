@@ -0,0 +1,16 @@
+import type { WorkerOptions } from 'node:worker_threads'
+
+import type { ExecuteTestOptions, PerfReportConfig, TestResult } from './index'
+
+/**
+ * Spawns a Node `worker_thread` that loads its own instance of the addon and executes a test
+ * synchronously on the worker's JS thread. Resolves with its result and rejects with the same
+ * error properties as `executeTestSync`
+ */
+export declare function executeTestInWorker(
+  testArtifactPath: string,
+  testName: string,
+  perfReportConfig?: PerfReportConfig | undefined | null,
+  options?: ExecuteTestOptions | undefined | null,
+  workerOptions?: Omit<WorkerOptions, 'workerData'> | undefined | null,
+): Promise<TestResult>
@@ -0,0 +1,45 @@
+const { Worker, isMainThread, parentPort, workerData } = require('node:worker_threads')
+
+const EXECUTE_TEST = 'napi-rs-revm:execute-test'
+
+// Runs in the worker: loads its own instance of the addon and executes the test on the worker's JS thread.
+if (!isMainThread && workerData?.task === EXECUTE_TEST) {
+  const { executeTestSync } = require('./index.js')
+  const { testArtifactPath, testName, perfReportConfig, options } = workerData
+  try {
+    parentPort.postMessage({ result: executeTestSync(testArtifactPath, testName, perfReportConfig, options) })
+  } catch (err) {
+    // Structured clone drops the properties of errors, so the details are sent as a plain object.
+    parentPort.postMessage({ error: { ...err, message: err.message } })
+  }
+}
+
+function executeTestInWorker(testArtifactPath, testName, perfReportConfig, options, workerOptions) {
+  return new Promise((resolve, reject) => {
+    const worker = new Worker(__filename, {
+      ...workerOptions,
+      workerData: { task: EXECUTE_TEST, testArtifactPath, testName, perfReportConfig, options },
+    })
+    let settled = false
+    worker.once('message', ({ result, error }) => {
+      settled = true
+      if (error) {
+        const { message, ...details } = error
+        reject(Object.assign(new Error(message), details))
+      } else {
+        resolve(result)
+      }
+    })
+    worker.once('error', (err) => {
+      settled = true
+      reject(err)
+    })
+    worker.once('exit', (code) => {
+      if (!settled) {
+        reject(new Error(`Worker exited with code ${code} before reporting a result`))
+      }
+    })
+  })
+}
+
+module.exports.executeTestInWorker = executeTestInWorker